@@ -0,0 +1 @@
+ALTER TABLE Flights ADD COLUMN position_time TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+ALTER TABLE Sessions ADD COLUMN expires_at TIMESTAMPTZ;
+ALTER TABLE Sessions ADD PRIMARY KEY (provider, bot_type);
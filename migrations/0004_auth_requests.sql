@@ -0,0 +1,6 @@
+CREATE TABLE AuthRequests (
+    state TEXT PRIMARY KEY,
+    bot_type BotType NOT NULL,
+    pkce_verifier TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,9 @@
+CREATE TYPE PostStatus AS ENUM ('POSTED', 'DRY_RUN');
+
+CREATE TABLE Posts (
+    id BIGSERIAL PRIMARY KEY,
+    bot_type BotType NOT NULL,
+    text TEXT NOT NULL,
+    status PostStatus NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,12 @@
+ALTER TYPE PostStatus ADD VALUE 'SKIPPED';
+ALTER TYPE PostStatus ADD VALUE 'FAILED';
+
+ALTER TABLE Posts RENAME COLUMN created_at TO posted_at;
+ALTER TABLE Posts
+    ADD COLUMN ident VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN tweet_id TEXT,
+    ADD COLUMN flight JSONB,
+    ADD COLUMN error TEXT;
+ALTER TABLE Posts ALTER COLUMN ident DROP DEFAULT;
+
+CREATE INDEX posts_bot_type_ident_idx ON Posts (bot_type, ident, posted_at);
@@ -0,0 +1,19 @@
+CREATE TABLE Observations (
+    id BIGSERIAL PRIMARY KEY,
+    bot_type BotType NOT NULL,
+    ident VARCHAR(255) NOT NULL,
+    fa_flight_id VARCHAR(255),
+    altitude INT,
+    groundspeed INT,
+    latitude DOUBLE PRECISION,
+    longitude DOUBLE PRECISION,
+    heading INT,
+    aircraft_type VARCHAR(16),
+    position_time TIMESTAMPTZ,
+    fetched_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX observations_fetched_at_idx ON Observations (fetched_at);
+CREATE INDEX observations_ident_idx ON Observations (ident, position_time);
+CREATE INDEX observations_fa_flight_id_idx ON Observations (fa_flight_id) WHERE fa_flight_id IS NOT NULL;
+CREATE INDEX observations_bot_type_idx ON Observations (bot_type, fetched_at);
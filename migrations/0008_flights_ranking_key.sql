@@ -0,0 +1,3 @@
+-- The same aircraft can lead several rankings at once.
+ALTER TABLE Flights DROP CONSTRAINT flights_pkey;
+ALTER TABLE Flights ADD PRIMARY KEY (ranking, ident);
@@ -0,0 +1,7 @@
+-- Posts doubles as the outbox of announcements waiting to be tweeted.
+ALTER TYPE PostStatus ADD VALUE 'PENDING';
+ALTER TABLE Posts
+    ADD COLUMN attempts INT NOT NULL DEFAULT 0,
+    ADD COLUMN delivered_at TIMESTAMPTZ;
+UPDATE Posts SET attempts = 1, delivered_at = posted_at WHERE status = 'POSTED';
+CREATE INDEX posts_bot_type_status_idx ON Posts (bot_type, status, id);
@@ -0,0 +1,4 @@
+-- Callsigns are reused across days, AeroAPI's flight id identifies a single leg.
+ALTER TABLE Flights ADD COLUMN fa_flight_id VARCHAR(255);
+ALTER TABLE Posts ADD COLUMN fa_flight_id VARCHAR(255);
+CREATE INDEX posts_bot_type_fa_flight_id_idx ON Posts (bot_type, fa_flight_id, posted_at);
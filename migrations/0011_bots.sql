@@ -0,0 +1,8 @@
+-- Runtime overrides of the bots' secrets, picked up on the next tick.
+CREATE TABLE Bots (
+    bot_type BotType PRIMARY KEY,
+    schedule TEXT,
+    threshold INT,
+    enabled BOOLEAN,
+    last_run_at TIMESTAMPTZ
+);
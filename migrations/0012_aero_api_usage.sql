@@ -0,0 +1,7 @@
+-- AeroAPI spend per calendar month (UTC), in millionths of a dollar.
+CREATE TABLE AeroApiUsage (
+    month DATE PRIMARY KEY,
+    result_sets INT NOT NULL DEFAULT 0,
+    -- Includes pages reserved by searches still running.
+    cost BIGINT NOT NULL DEFAULT 0
+);
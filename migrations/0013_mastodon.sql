@@ -0,0 +1,22 @@
+ALTER TYPE AuthProvider ADD VALUE 'MASTODON';
+
+-- Mastodon access tokens don't expire and come without a refresh token.
+ALTER TABLE Sessions ALTER COLUMN refresh_token DROP NOT NULL;
+
+ALTER TABLE AuthRequests ADD COLUMN provider AuthProvider NOT NULL DEFAULT 'X';
+
+-- OAuth apps registered on Mastodon instances, one per instance.
+CREATE TABLE MastodonApps (
+    instance_url TEXT PRIMARY KEY,
+    client_id TEXT NOT NULL,
+    client_secret TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Every announcement is delivered to each network of a bot as its own row.
+CREATE SEQUENCE announcement_ids;
+ALTER TABLE Posts
+    ADD COLUMN provider AuthProvider,
+    ADD COLUMN announcement_id BIGINT NOT NULL DEFAULT nextval('announcement_ids');
+ALTER TABLE Posts RENAME COLUMN tweet_id TO external_id;
+UPDATE Posts SET provider = 'X' WHERE status IN ('POSTED', 'PENDING', 'FAILED');
@@ -0,0 +1,7 @@
+ALTER TYPE AuthProvider ADD VALUE 'BLUESKY';
+
+-- Bluesky sessions are opened with an app password, kept to open a new one once the refresh
+-- token expires. The account's DID names the repository posts are written to.
+ALTER TABLE Sessions
+    ADD COLUMN account_id TEXT,
+    ADD COLUMN app_password TEXT;
@@ -0,0 +1,2 @@
+-- Announcements are delivered to a bot's webhooks like to any other network.
+ALTER TYPE AuthProvider ADD VALUE 'WEBHOOK';
@@ -0,0 +1,5 @@
+-- Type of the ranked aircraft, for the announcement templates.
+ALTER TABLE Flights ADD COLUMN aircraft_type VARCHAR(16);
+
+-- Runtime override of a bot's announcement template.
+ALTER TABLE Bots ADD COLUMN template TEXT;
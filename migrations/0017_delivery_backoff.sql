@@ -0,0 +1,2 @@
+-- When a failed delivery is tried again, unset while it is due.
+ALTER TABLE Posts ADD COLUMN next_attempt_at TIMESTAMPTZ;
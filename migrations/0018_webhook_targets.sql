@@ -0,0 +1,2 @@
+-- Every webhook of an announcement gets a row of its own, so a failing one is retried alone.
+ALTER TABLE Posts ADD COLUMN target TEXT;
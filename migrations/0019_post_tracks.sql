@@ -0,0 +1,2 @@
+-- Positions of the announced leg, drawn as a map on the networks that take images.
+ALTER TABLE Posts ADD COLUMN track JSONB;
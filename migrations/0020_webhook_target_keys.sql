@@ -0,0 +1,3 @@
+-- Rows name their webhook by a hash of its URL, since the URL itself lets anyone post to it.
+UPDATE Posts SET target = left(encode(sha256(convert_to(target, 'UTF8')), 'hex'), 16)
+WHERE target IS NOT NULL;
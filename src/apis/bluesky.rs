@@ -0,0 +1,700 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::{Client, StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use shuttle_runtime::{async_trait, SecretStore};
+use sqlx::PgPool;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{
+    responses::{timestamp_reset, Responses},
+    session::{SessionTokens, TokenExchange, Tokens},
+    Media, Publisher,
+};
+use crate::{
+    error::{BotError, PublishError},
+    types::{AuthProvider, BotType, Session},
+};
+
+/// Record collection of posts.
+const POST_COLLECTION: &str = "app.bsky.feed.post";
+
+/// Longest text of a post Bluesky accepts.
+const MAX_POST_GRAPHEMES: usize = 300;
+
+/// Longest text shown for a link. As long as X counts a link, so a text that fits a tweet fits
+/// a post too.
+const LINK_LABEL_LENGTH: usize = 23;
+
+/// The Bluesky account a bot announces on.
+#[derive(Clone, Debug)]
+pub struct BlueskyCredentials {
+    /// The account's PDS, e.g. `https://bsky.social`.
+    pub service_url: String,
+}
+
+impl BlueskyCredentials {
+    /// Reads the optional `BLUESKY_{key}_SERVICE_URL` secret; bots without it don't post to
+    /// Bluesky.
+    pub fn from_secrets(secrets: &SecretStore, key: &str) -> Result<Option<Self>, BotError> {
+        let secret = format!("BLUESKY_{}_SERVICE_URL", key);
+        let Some(service_url) = secrets.get(&secret) else {
+            return Ok(None);
+        };
+
+        Url::parse(&service_url)
+            .map_err(|e| BotError::Config(format!("invalid `{}`: {}", secret, e)))?;
+
+        Ok(Some(Self {
+            service_url: service_url.trim_end_matches('/').to_string(),
+        }))
+    }
+}
+
+/// Tokens returned by `createSession` and `refreshSession`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AtSession {
+    access_jwt: String,
+    refresh_jwt: String,
+    did: String,
+}
+
+/// Reference to a record, as returned when it is created.
+#[derive(Debug, Deserialize, Serialize)]
+struct StrongRef {
+    uri: String,
+    cid: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Record {
+    uri: String,
+    cid: String,
+    value: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadedBlob {
+    blob: Value,
+}
+
+#[derive(Debug)]
+pub struct BlueskyApi {
+    pub url: String,
+    pub client: Client,
+    /// DID of the account, i.e. the repository posts are written to.
+    did: String,
+    session: SessionTokens,
+}
+
+impl BlueskyApi {
+    /// Opens the bot's stored Bluesky session, refreshing its access token if needed.
+    ///
+    /// Fails with [`BotError::NotAuthorized`] until the bot has been onboarded via
+    /// `/auth/{bot}/bluesky/start`.
+    pub async fn new(
+        credentials: &BlueskyCredentials,
+        bot_type: BotType,
+        pool: &PgPool,
+    ) -> Result<Self, BotError> {
+        let (session, stored) = SessionTokens::load(AuthProvider::BLUESKY, bot_type, pool).await?;
+
+        let did = stored
+            .account_id
+            .clone()
+            .ok_or_else(|| BotError::Auth(AuthProvider::BLUESKY, "no DID stored".to_string()))?;
+
+        let bluesky_api = Self {
+            url: credentials.service_url.clone(),
+            client: Client::new(),
+            did,
+            session,
+        };
+        bluesky_api
+            .session
+            .refresh_if_expired(&bluesky_api, &stored)
+            .await?;
+
+        Ok(bluesky_api)
+    }
+
+    /// Whether the bot has been onboarded, without touching its tokens.
+    pub async fn is_authorized(bot_type: &BotType, pool: &PgPool) -> Result<bool, BotError> {
+        Ok(sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM Sessions WHERE Sessions.provider = 'BLUESKY' AND Sessions.bot_type = $1);",
+        )
+        .bind(bot_type)
+        .fetch_one(pool)
+        .await?)
+    }
+
+    /// Logs in with an app password and stores the session, along with the password to log in
+    /// again once the refresh token expired.
+    pub async fn authorize(
+        credentials: &BlueskyCredentials,
+        bot_type: BotType,
+        identifier: &str,
+        app_password: &str,
+        pool: &PgPool,
+    ) -> Result<(), BotError> {
+        let session = create_session(
+            &Client::new(),
+            &credentials.service_url,
+            identifier,
+            app_password,
+        )
+        .await?;
+
+        sqlx::query("INSERT INTO Sessions (provider, bot_type, access_token, refresh_token, expires_at, account_id, app_password) VALUES ($1, $2, $3, $4, $5, $6, $7) \
+            ON CONFLICT (provider, bot_type) DO UPDATE SET access_token = EXCLUDED.access_token, refresh_token = EXCLUDED.refresh_token, expires_at = EXCLUDED.expires_at, \
+            account_id = EXCLUDED.account_id, app_password = EXCLUDED.app_password;")
+            .bind(AuthProvider::BLUESKY)
+            .bind(bot_type)
+            .bind(&session.access_jwt)
+            .bind(&session.refresh_jwt)
+            .bind(expires_at(&session.access_jwt))
+            .bind(&session.did)
+            .bind(app_password)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn refresh_session(&self, refresh_token: &str) -> Result<AtSession, PublishError> {
+        let response = self
+            .client
+            .post(format!(
+                "{}/xrpc/com.atproto.server.refreshSession",
+                &self.url
+            ))
+            .bearer_auth(refresh_token)
+            .send()
+            .await?;
+
+        Ok(RESPONSES.check(response).await?.json().await?)
+    }
+
+    /// Calls an XRPC procedure and parses its output.
+    async fn procedure<T: DeserializeOwned>(
+        &self,
+        access_token: String,
+        method: &str,
+        input: &Value,
+    ) -> Result<T, PublishError> {
+        let response = self
+            .client
+            .post(format!("{}/xrpc/{}", &self.url, method))
+            .bearer_auth(access_token)
+            .json(input)
+            .send()
+            .await?;
+
+        Ok(RESPONSES.check(response).await?.json().await?)
+    }
+
+    async fn create_post(&self, record: Value) -> Result<String, BotError> {
+        let input = json!({ "repo": self.did, "collection": POST_COLLECTION, "record": record });
+
+        let created: StrongRef = self
+            .session
+            .authorized(self, |access_token| {
+                self.procedure(access_token, "com.atproto.repo.createRecord", &input)
+            })
+            .await?;
+
+        Ok(created.uri)
+    }
+
+    async fn get_post(&self, access_token: String, uri: &str) -> Result<Record, PublishError> {
+        let (repo, collection, rkey) = parse_post_uri(uri)?;
+
+        let response = self
+            .client
+            .get(format!("{}/xrpc/com.atproto.repo.getRecord", &self.url))
+            .query(&[("repo", repo), ("collection", collection), ("rkey", rkey)])
+            .bearer_auth(access_token)
+            .send()
+            .await?;
+
+        Ok(RESPONSES.check(response).await?.json().await?)
+    }
+
+    async fn upload_blob(
+        &self,
+        access_token: String,
+        media: &Media,
+    ) -> Result<Value, PublishError> {
+        let response = self
+            .client
+            .post(format!("{}/xrpc/com.atproto.repo.uploadBlob", &self.url))
+            .bearer_auth(access_token)
+            .header("content-type", &media.mime_type)
+            .body(media.bytes.clone())
+            .send()
+            .await?;
+
+        let uploaded = RESPONSES
+            .check(response)
+            .await?
+            .json::<UploadedBlob>()
+            .await?;
+        Ok(uploaded.blob)
+    }
+}
+
+#[async_trait]
+impl TokenExchange for BlueskyApi {
+    /// Refreshes the session, or logs in again with the app password once the refresh token
+    /// expired too.
+    async fn exchange(&self, session: Session) -> Result<Tokens, BotError> {
+        let refreshed = match &session.refresh_token {
+            Some(refresh_token) => self.refresh_session(refresh_token).await,
+            None => Err(PublishError::Unauthorized),
+        };
+
+        let tokens = match refreshed {
+            Ok(tokens) => tokens,
+            Err(PublishError::Unauthorized) => {
+                let app_password = session.app_password.ok_or_else(|| {
+                    BotError::Auth(AuthProvider::BLUESKY, "no app password stored".to_string())
+                })?;
+                create_session(&self.client, &self.url, &self.did, &app_password).await?
+            }
+            Err(e) => {
+                return Err(BotError::Auth(
+                    AuthProvider::BLUESKY,
+                    format!("refreshing the session: {}", e),
+                ))
+            }
+        };
+
+        Ok(Tokens {
+            expires_at: expires_at(&tokens.access_jwt),
+            access_token: tokens.access_jwt,
+            refresh_token: tokens.refresh_jwt,
+        })
+    }
+}
+
+#[async_trait]
+impl Publisher for BlueskyApi {
+    fn provider(&self) -> AuthProvider {
+        AuthProvider::BLUESKY
+    }
+
+    /// Records have no idempotency keys, a repeated delivery posts again.
+    async fn post(&self, text: &str, _key: &str) -> Result<String, BotError> {
+        self.create_post(post_record(text)).await
+    }
+
+    async fn post_with_media(
+        &self,
+        text: &str,
+        media: &[Media],
+        _key: &str,
+    ) -> Result<String, BotError> {
+        let mut images = vec![];
+
+        for media in media {
+            let blob = self
+                .session
+                .authorized(self, |access_token| self.upload_blob(access_token, media))
+                .await
+                .map_err(BotError::into_media_upload)?;
+            images.push(
+                json!({ "alt": media.alt_text.as_deref().unwrap_or_default(), "image": blob }),
+            );
+        }
+
+        let mut record = post_record(text);
+        record["embed"] = json!({ "$type": "app.bsky.embed.images", "images": images });
+
+        self.create_post(record).await
+    }
+
+    /// Records have no idempotency keys, see [`Self::post`].
+    async fn reply(&self, in_reply_to: &str, text: &str, _key: &str) -> Result<String, BotError> {
+        let parent = self
+            .session
+            .authorized(self, |access_token| {
+                self.get_post(access_token, in_reply_to)
+            })
+            .await?;
+
+        let parent_ref = json!(StrongRef {
+            uri: parent.uri,
+            cid: parent.cid,
+        });
+        // Replies to a reply stay in the thread of the original post.
+        let root = parent.value["reply"]
+            .get("root")
+            .cloned()
+            .unwrap_or_else(|| parent_ref.clone());
+
+        let mut record = post_record(text);
+        record["reply"] = json!({ "root": root, "parent": parent_ref });
+
+        self.create_post(record).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), BotError> {
+        let (_, _, rkey) = parse_post_uri(id).map_err(publish_error)?;
+        let input = json!({ "repo": self.did, "collection": POST_COLLECTION, "rkey": rkey });
+
+        self.session
+            .authorized(self, |access_token| async {
+                self.procedure::<Value>(access_token, "com.atproto.repo.deleteRecord", &input)
+                    .await
+                    .map(|_| ())
+            })
+            .await
+    }
+}
+
+async fn create_session(
+    client: &Client,
+    service_url: &str,
+    identifier: &str,
+    app_password: &str,
+) -> Result<AtSession, BotError> {
+    let auth_error =
+        |e: PublishError| BotError::Auth(AuthProvider::BLUESKY, format!("logging in: {}", e));
+
+    let response = client
+        .post(format!(
+            "{}/xrpc/com.atproto.server.createSession",
+            service_url
+        ))
+        .json(&json!({ "identifier": identifier, "password": app_password }))
+        .send()
+        .await
+        .map_err(|e| auth_error(e.into()))?;
+
+    RESPONSES
+        .check(response)
+        .await
+        .map_err(auth_error)?
+        .json()
+        .await
+        .map_err(|e| auth_error(e.into()))
+}
+
+fn post_record(text: &str) -> Value {
+    let (text, facets) = linked_text(text);
+
+    json!({
+        "$type": POST_COLLECTION,
+        "text": text,
+        "createdAt": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "facets": facets,
+    })
+}
+
+/// Fits `text` into a post: Bluesky doesn't detect links on its own and counts them in full, so
+/// the URLs are shown shortened like in its app and marked as links to the full URL. Whatever
+/// still exceeds [`MAX_POST_GRAPHEMES`] is cut off.
+///
+/// Facets index into the UTF-8 bytes of the text.
+fn linked_text(text: &str) -> (String, Vec<Value>) {
+    let mut linked = String::new();
+    let mut facets = vec![];
+    let mut start = 0;
+
+    while let Some(found) = text[start..].find("http") {
+        let begin = start + found;
+        let end = text[begin..]
+            .find(char::is_whitespace)
+            .map_or(text.len(), |len| begin + len);
+        let uri = &text[begin..end];
+
+        linked.push_str(&text[start..begin]);
+
+        match uri
+            .strip_prefix("https://")
+            .or_else(|| uri.strip_prefix("http://"))
+        {
+            Some(address) => {
+                let byte_start = linked.len();
+                linked.push_str(&link_label(address));
+                facets.push(json!({
+                    "index": { "byteStart": byte_start, "byteEnd": linked.len() },
+                    "features": [{ "$type": "app.bsky.richtext.facet#link", "uri": uri }],
+                }));
+            }
+            None => linked.push_str(uri),
+        }
+
+        start = end;
+    }
+    linked.push_str(&text[start..]);
+
+    let graphemes: Vec<&str> = linked.graphemes(true).collect();
+    if graphemes.len() > MAX_POST_GRAPHEMES {
+        let cut = graphemes[..MAX_POST_GRAPHEMES - 1].concat();
+        // A link cut in half would point somewhere its text doesn't show.
+        facets.retain(|facet| facet["index"]["byteEnd"].as_u64() <= Some(cut.len() as u64));
+        linked = cut.trim_end().to_string() + "…";
+    }
+
+    (linked, facets)
+}
+
+/// `address` without its `www.`, cut to [`LINK_LABEL_LENGTH`].
+fn link_label(address: &str) -> String {
+    let address = address.strip_prefix("www.").unwrap_or(address);
+    let graphemes: Vec<&str> = address.graphemes(true).collect();
+
+    if graphemes.len() <= LINK_LABEL_LENGTH {
+        return address.to_string();
+    }
+
+    graphemes[..LINK_LABEL_LENGTH - 1].concat() + "…"
+}
+
+/// Splits an `at://{repo}/{collection}/{rkey}` post URI.
+fn parse_post_uri(uri: &str) -> Result<(&str, &str, &str), PublishError> {
+    uri.strip_prefix("at://")
+        .and_then(|path| {
+            let mut parts = path.splitn(3, '/');
+            Some((parts.next()?, parts.next()?, parts.next()?))
+        })
+        .filter(|(_, collection, _)| *collection == POST_COLLECTION)
+        .ok_or_else(|| PublishError::Other {
+            status: StatusCode::BAD_REQUEST,
+            body: format!("not a post URI: {}", uri),
+        })
+}
+
+/// Reads the expiry out of an access token without verifying it.
+fn expires_at(jwt: &str) -> Option<DateTime<Utc>> {
+    #[derive(Deserialize)]
+    struct Claims {
+        exp: i64,
+    }
+
+    let payload = URL_SAFE_NO_PAD.decode(jwt.split('.').nth(1)?).ok()?;
+    let claims: Claims = serde_json::from_slice(&payload).ok()?;
+    DateTime::from_timestamp(claims.exp, 0)
+}
+
+fn publish_error(e: PublishError) -> BotError {
+    BotError::Publish(AuthProvider::BLUESKY, e)
+}
+
+/// XRPC reports expired tokens as bad requests.
+const RESPONSES: Responses = Responses {
+    reset: |headers| timestamp_reset(headers, "ratelimit-reset"),
+    classify: |status, body| {
+        let error = serde_json::from_str::<Value>(body).ok()?;
+        (status == StatusCode::BAD_REQUEST
+            && matches!(
+                error["error"].as_str(),
+                Some("ExpiredToken" | "InvalidToken")
+            ))
+        .then_some(PublishError::Unauthorized)
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bots::{AltitudeBot, RankingBot},
+        templates::TweetTemplate,
+        test_support::{insert_bluesky_session, valid_expiry, MockBluesky},
+        types::{Flight, Post, PostStatus},
+    };
+
+    async fn refresh_token(pool: &PgPool) -> String {
+        sqlx::query_scalar("SELECT refresh_token FROM Sessions WHERE provider = 'BLUESKY';")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn links_shortened_urls_by_byte_offsets() {
+        let text = "Höchster Flug ✈ N650GD\nhttps://www.flightaware.com/live/flight/N650GD";
+
+        let (linked, facets) = linked_text(text);
+
+        assert_eq!(linked, "Höchster Flug ✈ N650GD\nflightaware.com/live/f…");
+        assert_eq!(facets.len(), 1);
+        let start = facets[0]["index"]["byteStart"].as_u64().unwrap() as usize;
+        let end = facets[0]["index"]["byteEnd"].as_u64().unwrap() as usize;
+        assert_eq!(&linked[start..end], "flightaware.com/live/f…");
+        assert_eq!(
+            facets[0]["features"][0]["uri"],
+            "https://www.flightaware.com/live/flight/N650GD"
+        );
+    }
+
+    #[test]
+    fn text_without_links_has_no_facets() {
+        let text = "Altitude: 51000ft, http is not a link";
+
+        assert_eq!(linked_text(text), (text.to_string(), vec![]));
+    }
+
+    #[test]
+    fn cuts_text_past_the_limit() {
+        let text = format!("{} https://example.com", "✈️".repeat(400));
+
+        let (linked, facets) = linked_text(&text);
+
+        assert_eq!(linked.graphemes(true).count(), MAX_POST_GRAPHEMES);
+        assert!(linked.ends_with("✈️…"));
+        assert!(facets.is_empty());
+    }
+
+    #[test]
+    fn parses_post_uris() {
+        assert_eq!(
+            parse_post_uri("at://did:plc:abc/app.bsky.feed.post/3kxyz").unwrap(),
+            ("did:plc:abc", "app.bsky.feed.post", "3kxyz")
+        );
+        assert!(parse_post_uri("at://did:plc:abc/app.bsky.feed.like/3kxyz").is_err());
+        assert!(parse_post_uri("https://bsky.app").is_err());
+    }
+
+    #[test]
+    fn reads_token_expiry() {
+        let claims = URL_SAFE_NO_PAD.encode(r#"{"scope":"com.atproto.appPass","exp":1717167200}"#);
+        let jwt = format!("eyJhbGciOiJIUzI1NiJ9.{}.signature", claims);
+
+        assert_eq!(expires_at(&jwt), DateTime::from_timestamp(1717167200, 0));
+        assert_eq!(expires_at("not-a-jwt"), None);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn posts_links_and_threads_replies(pool: PgPool) {
+        let bluesky = MockBluesky::start();
+        insert_bluesky_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        let api = BlueskyApi::new(&bluesky.credentials(), BotType::ALTITUDE, &pool)
+            .await
+            .unwrap();
+
+        let uri = api
+            .post(
+                "Current highest flight: N650GD\nhttps://www.flightaware.com/live/flight/N650GD",
+                "1",
+            )
+            .await
+            .unwrap();
+        let reply = api.reply(&uri, "Landed", "2").await.unwrap();
+        let nested = api.reply(&reply, "Departed again", "3").await.unwrap();
+        api.delete(&nested).await.unwrap();
+
+        let records = bluesky.records();
+        assert_eq!(records[0]["$type"], POST_COLLECTION);
+        assert_eq!(
+            records[0]["facets"][0]["features"][0]["uri"],
+            "https://www.flightaware.com/live/flight/N650GD"
+        );
+        assert_eq!(records[1]["reply"]["parent"]["uri"], uri);
+        // The nested reply stays in the original post's thread.
+        assert_eq!(records[2]["reply"]["root"]["uri"], uri);
+        assert_eq!(records[2]["reply"]["parent"]["uri"], reply);
+        assert_eq!(bluesky.deleted(), ["3"]);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn fits_announcement_with_long_names(pool: PgPool) {
+        let bluesky = MockBluesky::start();
+        insert_bluesky_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        let api = BlueskyApi::new(&bluesky.credentials(), BotType::ALTITUDE, &pool)
+            .await
+            .unwrap();
+        let flight = Flight {
+            ident: "N650GD".to_string(),
+            fa_flight_id: Some("N650GD-1717160000-adhoc-0001".to_string()),
+            ranking: BotType::ALTITUDE,
+            altitude: Some(510),
+            groundspeed: Some(480),
+            origin: Some(
+                "Aeropuerto Internacional de la Ciudad de México Benito Juárez [MMMX]".repeat(2),
+            ),
+            destination: Some(
+                "Flughafen Berlin Brandenburg Willy Brandt, Schönefeld [EDDB]".repeat(2),
+            ),
+            aircraft_type: None,
+            position_time: None,
+        };
+        let template: TweetTemplate = AltitudeBot.default_template().parse().unwrap();
+        let text = template.render(&flight);
+        // Fits a tweet, where the link counts 23 characters, but not a post with the full URL.
+        assert!(text.graphemes(true).count() > MAX_POST_GRAPHEMES);
+
+        api.announce(&Post {
+            id: 1,
+            bot_type: BotType::ALTITUDE,
+            ident: flight.ident.clone(),
+            fa_flight_id: flight.fa_flight_id.clone(),
+            provider: Some(AuthProvider::BLUESKY),
+            target: None,
+            announcement_id: 1,
+            external_id: None,
+            text,
+            flight: Some(sqlx::types::Json(flight.clone())),
+            track: None,
+            status: PostStatus::PENDING,
+            error: None,
+            posted_at: Utc::now(),
+            attempts: 0,
+            next_attempt_at: None,
+            delivered_at: None,
+        })
+        .await
+        .unwrap();
+
+        let record = &bluesky.records()[0];
+        let text = record["text"].as_str().unwrap();
+        assert!(text.graphemes(true).count() <= MAX_POST_GRAPHEMES);
+        assert!(text.starts_with("Current highest flight: N650GD"));
+        assert!(text.ends_with("More info:\nflightaware.com/live/f…"));
+        assert_eq!(
+            record["facets"][0]["features"][0]["uri"],
+            flight.flightaware_url()
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn refreshes_expired_access_token(pool: PgPool) {
+        let bluesky = MockBluesky::start();
+        insert_bluesky_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        let api = BlueskyApi::new(&bluesky.credentials(), BotType::ALTITUDE, &pool)
+            .await
+            .unwrap();
+
+        bluesky.expire_access_token();
+        api.post("Current highest flight: N650GD", "1")
+            .await
+            .unwrap();
+
+        assert_eq!(bluesky.refreshes(), 1);
+        assert_eq!(bluesky.records().len(), 1);
+        assert_eq!(refresh_token(&pool).await, "refresh-1");
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn logs_in_again_once_refresh_token_expired(pool: PgPool) {
+        let bluesky = MockBluesky::start();
+        insert_bluesky_session(&pool, BotType::ALTITUDE, Utc::now()).await;
+        bluesky.expire_refresh_token();
+
+        BlueskyApi::new(&bluesky.credentials(), BotType::ALTITUDE, &pool)
+            .await
+            .unwrap();
+
+        assert_eq!(bluesky.refreshes(), 0);
+        assert_eq!(bluesky.logins(), 1);
+        assert_eq!(refresh_token(&pool).await, "refresh-1");
+    }
+}
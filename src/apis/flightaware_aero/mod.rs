@@ -0,0 +1,126 @@
+mod models;
+
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use models::{FlightSearchResponse, FlightTrackResponse, Position, SearchFlight};
+use reqwest::Client;
+
+use crate::{error::BotError, types::TrackPoint};
+
+pub const DEFAULT_AERO_API_URL: &str = "https://aeroapi.flightaware.com/aeroapi";
+
+/// Pages fetched per search unless configured otherwise. Every page is billed.
+pub const DEFAULT_MAX_PAGES: u32 = 1;
+
+/// Price of a `/flights/search` result set (page) in millionths of a dollar, unless configured.
+pub const DEFAULT_RESULT_SET_COST: i64 = 5_000;
+
+pub struct AeroApi {
+    client: Client,
+    url: String,
+    api_key: String,
+    /// Price of a result set in millionths of a dollar.
+    result_set_cost: i64,
+    pages_fetched: AtomicU32,
+}
+
+impl AeroApi {
+    pub fn new(url: String, api_key: String, result_set_cost: i64) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+            api_key,
+            result_set_cost,
+            pages_fetched: AtomicU32::new(0),
+        }
+    }
+
+    /// Number of result sets, i.e. search pages and tracks, fetched by this client so far.
+    pub fn pages_fetched(&self) -> u32 {
+        self.pages_fetched.load(Ordering::Relaxed)
+    }
+
+    /// What the result sets fetched so far cost, in millionths of a dollar.
+    pub fn spent(&self) -> i64 {
+        i64::from(self.pages_fetched()) * self.result_set_cost
+    }
+
+    /// Runs a `/flights/search` query, following `links.next` for at most `max_pages` pages.
+    ///
+    /// Flights repeated across pages are only returned once.
+    pub async fn search_flights(
+        &self,
+        query: &str,
+        max_pages: u32,
+    ) -> Result<Vec<SearchFlight>, BotError> {
+        let mut request = self
+            .client
+            .get(format!("{}/flights/search", &self.url))
+            .query(&[("query", query)]);
+
+        let mut seen = HashSet::new();
+        let mut flights = vec![];
+
+        for _ in 0..max_pages {
+            let response = request
+                .header("x-apikey", &self.api_key)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(BotError::AeroApiHttp)?;
+
+            // Billed once AeroAPI answered, whether or not the body can be used.
+            self.pages_fetched.fetch_add(1, Ordering::Relaxed);
+
+            let body = response.text().await.map_err(BotError::AeroApiHttp)?;
+            let response: FlightSearchResponse =
+                serde_json::from_str(&body).map_err(BotError::AeroApiParse)?;
+
+            for flight in response.flights {
+                let key = flight
+                    .fa_flight_id
+                    .clone()
+                    .unwrap_or_else(|| flight.ident.clone());
+
+                if seen.insert(key) {
+                    flights.push(flight);
+                }
+            }
+
+            match response.links.and_then(|links| links.next) {
+                Some(next) => request = self.client.get(format!("{}{}", &self.url, next)),
+                None => break,
+            }
+        }
+
+        Ok(flights)
+    }
+
+    /// Fetches the positions reported of a flight leg so far, oldest first.
+    pub async fn flight_track(&self, fa_flight_id: &str) -> Result<Vec<TrackPoint>, BotError> {
+        let response = self
+            .client
+            .get(format!("{}/flights/{}/track", &self.url, fa_flight_id))
+            .header("x-apikey", &self.api_key)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(BotError::AeroApiHttp)?;
+
+        // Billed once AeroAPI answered, whether or not the body can be used.
+        self.pages_fetched.fetch_add(1, Ordering::Relaxed);
+
+        let body = response.text().await.map_err(BotError::AeroApiHttp)?;
+        let response: FlightTrackResponse =
+            serde_json::from_str(&body).map_err(BotError::AeroApiParse)?;
+
+        Ok(response
+            .positions
+            .iter()
+            .filter_map(Position::track_point)
+            .collect())
+    }
+}
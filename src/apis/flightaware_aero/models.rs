@@ -0,0 +1,282 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::types::{BotType, Flight, Observation, TrackPoint};
+
+/// Response of `GET /flights/search`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FlightSearchResponse {
+    pub links: Option<Links>,
+    #[serde(default)]
+    pub num_pages: u32,
+    #[serde(default)]
+    pub flights: Vec<SearchFlight>,
+}
+
+/// Response of `GET /flights/{id}/track`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FlightTrackResponse {
+    #[serde(default)]
+    pub positions: Vec<Position>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Links {
+    /// Path of the next result page, relative to the AeroAPI base URL.
+    pub next: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SearchFlight {
+    pub ident: String,
+    pub ident_icao: Option<String>,
+    pub ident_iata: Option<String>,
+    pub fa_flight_id: Option<String>,
+    pub actual_off: Option<DateTime<Utc>>,
+    pub actual_on: Option<DateTime<Utc>>,
+    pub origin: Option<AirportRef>,
+    pub destination: Option<AirportRef>,
+    pub first_position_time: Option<DateTime<Utc>>,
+    pub last_position: Option<Position>,
+    pub aircraft_type: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AirportRef {
+    pub code: Option<String>,
+    pub code_icao: Option<String>,
+    pub code_iata: Option<String>,
+    pub code_lid: Option<String>,
+    pub timezone: Option<String>,
+    pub name: Option<String>,
+    pub city: Option<String>,
+    pub airport_info_url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Position {
+    pub fa_flight_id: Option<String>,
+    /// Flight level (hundreds of feet).
+    pub altitude: Option<i32>,
+    pub altitude_change: Option<String>,
+    /// Knots.
+    pub groundspeed: Option<i32>,
+    pub heading: Option<i32>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub update_type: Option<String>,
+}
+
+impl AirportRef {
+    /// `"{name}, {city} [{code_icao}]"`, if all three are known.
+    pub fn describe(&self) -> Option<String> {
+        let name = self.name.as_ref()?;
+        let city = self.city.as_ref()?;
+        let code_icao = self.code_icao.as_ref()?;
+        Some(format!("{}, {} [{}]", name, city, code_icao))
+    }
+}
+
+impl Position {
+    /// The position as a point of a track, if it has coordinates.
+    pub fn track_point(&self) -> Option<TrackPoint> {
+        Some(TrackPoint {
+            latitude: self.latitude?,
+            longitude: self.longitude?,
+            heading: self.heading,
+        })
+    }
+}
+
+impl SearchFlight {
+    /// The flight's last position, if AeroAPI reported one.
+    pub fn observation(&self, bot_type: BotType, fetched_at: DateTime<Utc>) -> Option<Observation> {
+        let position = self.last_position.as_ref()?;
+
+        Some(Observation {
+            bot_type,
+            ident: self.ident.clone(),
+            fa_flight_id: self.fa_flight_id.clone(),
+            altitude: position.altitude,
+            groundspeed: position.groundspeed,
+            latitude: position.latitude,
+            longitude: position.longitude,
+            heading: position.heading,
+            aircraft_type: self.aircraft_type.clone(),
+            position_time: position.timestamp,
+            fetched_at,
+        })
+    }
+
+    pub fn into_flight(self, ranking: BotType) -> Flight {
+        let position = self.last_position.as_ref();
+
+        Flight {
+            ident: self.ident,
+            fa_flight_id: self.fa_flight_id,
+            ranking,
+            altitude: position.and_then(|p| p.altitude),
+            groundspeed: position.and_then(|p| p.groundspeed),
+            destination: self.destination.as_ref().and_then(AirportRef::describe),
+            origin: self.origin.as_ref().and_then(AirportRef::describe),
+            aircraft_type: self.aircraft_type,
+            position_time: position.and_then(|p| p.timestamp),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const SEARCH_ALTITUDE: &str =
+        include_str!("../../../tests/fixtures/aeroapi/search_altitude.json");
+    const SEARCH_PAGINATED: &str =
+        include_str!("../../../tests/fixtures/aeroapi/search_paginated.json");
+    const SEARCH_EMPTY: &str = include_str!("../../../tests/fixtures/aeroapi/search_empty.json");
+    const TRACK: &str = include_str!("../../../tests/fixtures/aeroapi/track_N650GD.json");
+
+    fn parse(json: &str) -> FlightSearchResponse {
+        serde_json::from_str(json).expect("fixture should parse")
+    }
+
+    #[test]
+    fn parses_full_flight() {
+        let response = parse(SEARCH_ALTITUDE);
+        let flight = &response.flights[0];
+
+        assert_eq!(flight.ident, "UAE215");
+        assert_eq!(
+            flight.fa_flight_id.as_deref(),
+            Some("UAE215-1717132800-schedule-0412")
+        );
+        assert_eq!(flight.aircraft_type.as_deref(), Some("A388"));
+
+        let position = flight.last_position.as_ref().unwrap();
+        assert_eq!(position.altitude, Some(470));
+        assert_eq!(position.groundspeed, Some(512));
+        assert_eq!(position.latitude, Some(63.41));
+        assert_eq!(
+            position.timestamp,
+            Some(Utc.with_ymd_and_hms(2024, 6, 1, 12, 4, 31).unwrap())
+        );
+
+        let origin = flight.origin.as_ref().unwrap();
+        assert_eq!(origin.code_iata.as_deref(), Some("DXB"));
+        assert_eq!(origin.code_lid, None);
+    }
+
+    #[test]
+    fn parses_flights_with_missing_fields() {
+        let response = parse(SEARCH_ALTITUDE);
+
+        let bizjet = &response.flights[1];
+        assert!(bizjet.destination.is_none());
+        assert_eq!(bizjet.last_position.as_ref().unwrap().groundspeed, None);
+
+        let balloon = &response.flights[2];
+        assert!(balloon.last_position.is_none());
+        assert!(balloon.origin.is_none());
+        assert!(balloon.first_position_time.is_none());
+    }
+
+    #[test]
+    fn parses_pagination_links() {
+        let response = parse(SEARCH_PAGINATED);
+
+        assert_eq!(
+            response.links.unwrap().next.as_deref(),
+            Some("/flights/search?query=-aboveGroundspeed+650&cursor=7f9e3c21aa")
+        );
+        assert_eq!(response.num_pages, 1);
+
+        let response = parse(SEARCH_ALTITUDE);
+        assert!(response.links.is_none());
+    }
+
+    #[test]
+    fn parses_empty_result() {
+        let response = parse(SEARCH_EMPTY);
+
+        assert!(response.flights.is_empty());
+        assert!(response.links.is_none());
+    }
+
+    #[test]
+    fn parses_track() {
+        let response: FlightTrackResponse = serde_json::from_str(TRACK).unwrap();
+
+        let track: Vec<TrackPoint> = response
+            .positions
+            .iter()
+            .filter_map(Position::track_point)
+            .collect();
+        assert_eq!(track.len(), 4);
+        assert_eq!(
+            track[0],
+            TrackPoint {
+                latitude: 40.86,
+                longitude: -73.98,
+                heading: Some(95),
+            }
+        );
+        assert_eq!(track[3].heading, None);
+    }
+
+    #[test]
+    fn converts_to_flight() {
+        let response = parse(SEARCH_ALTITUDE);
+        let flight = response.flights[0].clone().into_flight(BotType::ALTITUDE);
+
+        assert_eq!(
+            flight,
+            Flight {
+                ident: "UAE215".to_string(),
+                fa_flight_id: Some("UAE215-1717132800-schedule-0412".to_string()),
+                ranking: BotType::ALTITUDE,
+                altitude: Some(470),
+                groundspeed: Some(512),
+                destination: Some("Los Angeles Intl, Los Angeles [KLAX]".to_string()),
+                origin: Some("Dubai Int'l, Dubai [OMDB]".to_string()),
+                aircraft_type: Some("A388".to_string()),
+                position_time: Some(Utc.with_ymd_and_hms(2024, 6, 1, 12, 4, 31).unwrap()),
+            }
+        );
+    }
+
+    #[test]
+    fn converts_flight_without_position() {
+        let response = parse(SEARCH_ALTITUDE);
+        let flight = response.flights[2].clone().into_flight(BotType::ALTITUDE);
+
+        assert_eq!(flight.ident, "HBAL712");
+        assert_eq!(flight.altitude, None);
+        assert_eq!(flight.groundspeed, None);
+        assert_eq!(flight.origin, None);
+        assert_eq!(flight.position_time, None);
+    }
+
+    #[test]
+    fn observes_last_position() {
+        let response = parse(SEARCH_ALTITUDE);
+        let fetched_at = Utc.with_ymd_and_hms(2024, 6, 1, 12, 5, 0).unwrap();
+
+        let observation = response.flights[0]
+            .observation(BotType::ALTITUDE, fetched_at)
+            .unwrap();
+        assert_eq!(observation.ident, "UAE215");
+        assert_eq!(observation.altitude, Some(470));
+        assert_eq!(observation.latitude, Some(63.41));
+        assert_eq!(observation.aircraft_type.as_deref(), Some("A388"));
+        assert_eq!(observation.fetched_at, fetched_at);
+
+        // The balloon has no position to observe.
+        assert_eq!(
+            response.flights[2].observation(BotType::ALTITUDE, fetched_at),
+            None
+        );
+    }
+}
@@ -0,0 +1,453 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use oauth2::{
+    basic::BasicClient, reqwest::async_http_client, AuthType, AuthUrl, AuthorizationCode, ClientId,
+    ClientSecret, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
+    TokenResponse, TokenUrl,
+};
+use reqwest::{
+    multipart::{Form, Part},
+    Client, Url,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shuttle_runtime::{async_trait, tokio, SecretStore};
+use sqlx::PgPool;
+
+use super::{responses::Responses, Media, Publisher};
+use crate::{
+    error::{BotError, PublishError},
+    types::{AuthProvider, BotType, Session},
+};
+
+/// Scopes requested from the instance, both when registering the app and authorizing.
+const SCOPES: &str = "write:statuses write:media";
+
+/// Times an upload still being processed is polled before the status is posted anyway.
+const MEDIA_PROCESSING_POLLS: usize = 10;
+
+/// The Mastodon account a bot announces on.
+#[derive(Clone, Debug)]
+pub struct MastodonCredentials {
+    /// e.g. `https://mastodon.social`.
+    pub instance_url: String,
+    /// Where the instance sends the account owner back to, i.e. this service's `/callback`.
+    pub redirect_url: String,
+}
+
+impl MastodonCredentials {
+    /// Reads the optional `MASTODON_{key}_INSTANCE_URL` secret; bots without it only post to X.
+    pub fn from_secrets(
+        secrets: &SecretStore,
+        key: &str,
+        redirect_url: &str,
+    ) -> Result<Option<Self>, BotError> {
+        let secret = format!("MASTODON_{}_INSTANCE_URL", key);
+        let Some(instance_url) = secrets.get(&secret) else {
+            return Ok(None);
+        };
+
+        Url::parse(&instance_url)
+            .map_err(|e| BotError::Config(format!("invalid `{}`: {}", secret, e)))?;
+
+        Ok(Some(Self {
+            instance_url: instance_url.trim_end_matches('/').to_string(),
+            redirect_url: redirect_url.to_string(),
+        }))
+    }
+
+    /// Registers this service as an app on the instance, once per instance.
+    async fn app(&self, pool: &PgPool) -> Result<BasicClient, BotError> {
+        let app: Option<(String, String)> = sqlx::query_as(
+            "SELECT client_id, client_secret FROM MastodonApps WHERE instance_url = $1;",
+        )
+        .bind(&self.instance_url)
+        .fetch_optional(pool)
+        .await?;
+
+        let (client_id, client_secret) = match app {
+            Some(app) => app,
+            None => {
+                let app = self.register_app().await?;
+
+                // Another request may have registered the app meanwhile; the first one wins.
+                sqlx::query("INSERT INTO MastodonApps (instance_url, client_id, client_secret) VALUES ($1, $2, $3) \
+                    ON CONFLICT (instance_url) DO NOTHING;")
+                    .bind(&self.instance_url)
+                    .bind(&app.client_id)
+                    .bind(&app.client_secret)
+                    .execute(pool)
+                    .await?;
+
+                sqlx::query_as(
+                    "SELECT client_id, client_secret FROM MastodonApps WHERE instance_url = $1;",
+                )
+                .bind(&self.instance_url)
+                .fetch_one(pool)
+                .await?
+            }
+        };
+
+        let auth_url = AuthUrl::new(format!("{}/oauth/authorize", self.instance_url))
+            .map_err(|e| BotError::Config(format!("invalid Mastodon instance URL: {}", e)))?;
+        let token_url = TokenUrl::new(format!("{}/oauth/token", self.instance_url))
+            .map_err(|e| BotError::Config(format!("invalid Mastodon instance URL: {}", e)))?;
+        let redirect_url = RedirectUrl::new(self.redirect_url.clone())
+            .map_err(|e| BotError::Config(format!("invalid Mastodon redirect URL: {}", e)))?;
+
+        Ok(BasicClient::new(
+            ClientId::new(client_id),
+            Some(ClientSecret::new(client_secret)),
+            auth_url,
+            Some(token_url),
+        )
+        .set_auth_type(AuthType::RequestBody)
+        .set_redirect_uri(redirect_url))
+    }
+
+    async fn register_app(&self) -> Result<RegisteredApp, BotError> {
+        let auth_error = |e: PublishError| {
+            BotError::Auth(
+                AuthProvider::MASTODON,
+                format!("registering the app: {}", e),
+            )
+        };
+
+        let response = Client::new()
+            .post(format!("{}/api/v1/apps", self.instance_url))
+            .form(&[
+                ("client_name", "Aviation Bots"),
+                ("redirect_uris", &self.redirect_url),
+                ("scopes", SCOPES),
+            ])
+            .send()
+            .await
+            .map_err(|e| auth_error(e.into()))?;
+
+        RESPONSES
+            .check(response)
+            .await
+            .map_err(auth_error)?
+            .json()
+            .await
+            .map_err(|e| auth_error(e.into()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RegisteredApp {
+    client_id: String,
+    client_secret: String,
+}
+
+#[derive(Debug)]
+pub struct MastodonApi {
+    pub url: String,
+    pub client: Client,
+    access_token: String,
+}
+
+impl MastodonApi {
+    /// Opens the bot's stored Mastodon session.
+    ///
+    /// Fails with [`BotError::NotAuthorized`] until the bot has been onboarded via
+    /// `/auth/{bot}/mastodon/start`.
+    pub async fn new(
+        credentials: &MastodonCredentials,
+        bot_type: BotType,
+        pool: &PgPool,
+    ) -> Result<Self, BotError> {
+        let session: Session = sqlx::query_as(
+            "SELECT * FROM Sessions WHERE Sessions.provider = 'MASTODON' AND Sessions.bot_type = $1;",
+        )
+        .bind(&bot_type)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(BotError::NotAuthorized(bot_type, AuthProvider::MASTODON))?;
+
+        Ok(Self {
+            url: credentials.instance_url.clone(),
+            client: Client::new(),
+            access_token: session.access_token,
+        })
+    }
+
+    /// Whether the bot has been onboarded.
+    pub async fn is_authorized(bot_type: &BotType, pool: &PgPool) -> Result<bool, BotError> {
+        Ok(sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM Sessions WHERE Sessions.provider = 'MASTODON' AND Sessions.bot_type = $1);",
+        )
+        .bind(bot_type)
+        .fetch_one(pool)
+        .await?)
+    }
+
+    /// Builds the URL a bot account owner has to visit to grant the bot access, registering
+    /// the app on the instance first if needed.
+    ///
+    /// The returned state and PKCE verifier are needed to complete the authorization.
+    pub async fn authorize_url(
+        credentials: &MastodonCredentials,
+        pool: &PgPool,
+    ) -> Result<(Url, CsrfToken, PkceCodeVerifier), BotError> {
+        let (pkce_code_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+
+        let (auth_url, state) = credentials
+            .app(pool)
+            .await?
+            .authorize_url(CsrfToken::new_random)
+            .add_scopes(SCOPES.split(' ').map(|scope| Scope::new(scope.to_string())))
+            .set_pkce_challenge(pkce_code_challenge)
+            .url();
+
+        Ok((auth_url, state, pkce_verifier))
+    }
+
+    /// Exchanges the code the instance redirected back with for a token and stores it as the
+    /// bot's session. Mastodon tokens don't expire, so there is nothing to refresh.
+    pub async fn authorize(
+        credentials: &MastodonCredentials,
+        bot_type: BotType,
+        code: AuthorizationCode,
+        pkce_verifier: PkceCodeVerifier,
+        pool: &PgPool,
+    ) -> Result<(), BotError> {
+        let tokens = credentials
+            .app(pool)
+            .await?
+            .exchange_code(code)
+            .set_pkce_verifier(pkce_verifier)
+            .request_async(async_http_client)
+            .await
+            .map_err(|e| {
+                BotError::Auth(
+                    AuthProvider::MASTODON,
+                    format!("exchanging the authorization code: {}", e),
+                )
+            })?;
+
+        sqlx::query("INSERT INTO Sessions (provider, bot_type, access_token, refresh_token, expires_at) VALUES ($1, $2, $3, NULL, NULL) \
+            ON CONFLICT (provider, bot_type) DO UPDATE SET access_token = EXCLUDED.access_token, refresh_token = NULL, expires_at = NULL;")
+            .bind(AuthProvider::MASTODON)
+            .bind(bot_type)
+            .bind(tokens.access_token().secret())
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn create_status(&self, body: Value, key: Option<&str>) -> Result<String, BotError> {
+        let mut request = self
+            .client
+            .post(format!("{}/api/v1/statuses", &self.url))
+            .bearer_auth(&self.access_token)
+            .json(&body);
+
+        if let Some(key) = key {
+            request = request.header("Idempotency-Key", key);
+        }
+
+        let response = request.send().await.map_err(publish_error)?;
+        let created = RESPONSES
+            .check(response)
+            .await
+            .map_err(publish_error)?
+            .json::<Created>()
+            .await
+            .map_err(publish_error)?;
+
+        Ok(created.id)
+    }
+
+    /// Uploads an image and returns its media id once the instance has processed it.
+    async fn upload_media(&self, media: &Media) -> Result<String, PublishError> {
+        let part = Part::bytes(media.bytes.clone())
+            .file_name("media")
+            .mime_str(&media.mime_type)?;
+        let mut form = Form::new().part("file", part);
+
+        if let Some(alt_text) = &media.alt_text {
+            form = form.text("description", alt_text.clone());
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/api/v2/media", &self.url))
+            .bearer_auth(&self.access_token)
+            .multipart(form)
+            .send()
+            .await?;
+
+        let mut attachment = RESPONSES
+            .check(response)
+            .await?
+            .json::<Attachment>()
+            .await?;
+
+        // Larger uploads are processed asynchronously and can't be attached before they're done.
+        for _ in 0..MEDIA_PROCESSING_POLLS {
+            if attachment.url.is_some() {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+
+            let response = self
+                .client
+                .get(format!("{}/api/v1/media/{}", &self.url, attachment.id))
+                .bearer_auth(&self.access_token)
+                .send()
+                .await?;
+
+            attachment = RESPONSES.check(response).await?.json().await?;
+        }
+
+        Ok(attachment.id)
+    }
+}
+
+#[async_trait]
+impl Publisher for MastodonApi {
+    fn provider(&self) -> AuthProvider {
+        AuthProvider::MASTODON
+    }
+
+    async fn post(&self, text: &str, key: &str) -> Result<String, BotError> {
+        self.create_status(json!({ "status": text }), Some(key))
+            .await
+    }
+
+    async fn post_with_media(
+        &self,
+        text: &str,
+        media: &[Media],
+        key: &str,
+    ) -> Result<String, BotError> {
+        let mut media_ids = vec![];
+
+        for media in media {
+            let media_id = self
+                .upload_media(media)
+                .await
+                .map_err(publish_error)
+                .map_err(BotError::into_media_upload)?;
+            media_ids.push(media_id);
+        }
+
+        self.create_status(json!({ "status": text, "media_ids": media_ids }), Some(key))
+            .await
+    }
+
+    async fn reply(&self, in_reply_to: &str, text: &str, key: &str) -> Result<String, BotError> {
+        self.create_status(
+            json!({ "status": text, "in_reply_to_id": in_reply_to }),
+            Some(key),
+        )
+        .await
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), BotError> {
+        let response = self
+            .client
+            .delete(format!("{}/api/v1/statuses/{}", &self.url, id))
+            .bearer_auth(&self.access_token)
+            .send()
+            .await
+            .map_err(publish_error)?;
+
+        RESPONSES.check(response).await.map_err(publish_error)?;
+        Ok(())
+    }
+}
+
+fn publish_error(e: impl Into<PublishError>) -> BotError {
+    BotError::Publish(AuthProvider::MASTODON, e.into())
+}
+
+/// Mastodon reports when its rate limit resets as a date.
+const RESPONSES: Responses = Responses {
+    reset: |headers| {
+        let reset = DateTime::parse_from_rfc3339(headers.get("x-ratelimit-reset")?.to_str().ok()?);
+        Some(reset.ok()?.with_timezone(&Utc))
+    },
+    classify: |_, _| None,
+};
+
+#[derive(Debug, Deserialize)]
+struct Created {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Attachment {
+    id: String,
+    /// Missing while the upload is still being processed.
+    url: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use warp::http::StatusCode;
+
+    use super::*;
+    use crate::test_support::{insert_mastodon_session, MockMastodon};
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn replies_to_and_deletes_statuses(pool: PgPool) {
+        let mastodon = MockMastodon::start();
+        insert_mastodon_session(&pool, BotType::ALTITUDE).await;
+        let api = MastodonApi::new(&mastodon.credentials(), BotType::ALTITUDE, &pool)
+            .await
+            .unwrap();
+
+        let id = api.post("Current highest flight", "1").await.unwrap();
+        let reply = api.reply(&id, "Landed", "2").await.unwrap();
+        api.delete(&reply).await.unwrap();
+
+        assert_eq!(mastodon.requests()[1]["in_reply_to_id"], id);
+        assert_eq!(
+            mastodon.idempotency_keys(),
+            [Some("1".to_string()), Some("2".to_string())]
+        );
+        assert_eq!(mastodon.deleted(), [reply]);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn classifies_rejected_statuses(pool: PgPool) {
+        let mastodon = MockMastodon::start();
+        insert_mastodon_session(&pool, BotType::ALTITUDE).await;
+        let api = MastodonApi::new(&mastodon.credentials(), BotType::ALTITUDE, &pool)
+            .await
+            .unwrap();
+
+        mastodon.fail_next_status(StatusCode::TOO_MANY_REQUESTS);
+        assert!(matches!(
+            api.post("Current highest flight", "1").await,
+            Err(BotError::Publish(
+                AuthProvider::MASTODON,
+                PublishError::RateLimited { .. }
+            ))
+        ));
+
+        mastodon.fail_next_status(StatusCode::UNAUTHORIZED);
+        assert!(matches!(
+            api.post("Current highest flight", "1").await,
+            Err(BotError::Publish(_, PublishError::Unauthorized))
+        ));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn requires_onboarding(pool: PgPool) {
+        let mastodon = MockMastodon::start();
+
+        assert!(matches!(
+            MastodonApi::new(&mastodon.credentials(), BotType::ALTITUDE, &pool).await,
+            Err(BotError::NotAuthorized(_, AuthProvider::MASTODON))
+        ));
+    }
+}
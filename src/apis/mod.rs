@@ -1,6 +1,19 @@
+mod bluesky;
 mod flightaware_aero;
-
+mod mastodon;
+mod publisher;
+mod responses;
+mod session;
+mod webhook;
 mod x;
 
-pub use flightaware_aero::AeroApi;
-pub use x::XApi;
+pub use bluesky::{BlueskyApi, BlueskyCredentials};
+pub use flightaware_aero::{
+    AeroApi, DEFAULT_AERO_API_URL, DEFAULT_MAX_PAGES, DEFAULT_RESULT_SET_COST,
+};
+pub use mastodon::{MastodonApi, MastodonCredentials};
+pub use publisher::{Media, Publisher};
+#[cfg(test)]
+pub use webhook::WebhookFormat;
+pub use webhook::{WebhookConfig, WebhookPublisher, WebhookTarget};
+pub use x::{XApi, XCredentials, XEndpoints};
@@ -0,0 +1,49 @@
+use shuttle_runtime::async_trait;
+
+use crate::{
+    error::BotError,
+    types::{AuthProvider, Post},
+};
+
+/// An image attached to a post.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Media {
+    pub bytes: Vec<u8>,
+    pub mime_type: String,
+    /// Description for screen readers.
+    pub alt_text: Option<String>,
+}
+
+/// A social network the bots announce on.
+///
+/// Every method returns or takes the network's own id of the post. Networks that can't do
+/// something fail with [`crate::error::PublishError::Unsupported`].
+#[async_trait]
+pub trait Publisher: Send + Sync {
+    fn provider(&self) -> AuthProvider;
+
+    /// Delivers a queued announcement. Networks that only take text post its rendered text,
+    /// keyed by the row so a retried delivery can be recognized.
+    async fn announce(&self, post: &Post) -> Result<String, BotError> {
+        self.post(&post.text, &post.id.to_string()).await
+    }
+
+    /// Publishes `text`. `key` identifies the announcement, so networks that support it can
+    /// drop a repeated delivery.
+    async fn post(&self, text: &str, key: &str) -> Result<String, BotError>;
+
+    /// Publishes `text` with `media` attached. Fails with
+    /// [`crate::error::PublishError::MediaUpload`] before anything is posted if the network
+    /// rejects an image.
+    async fn post_with_media(
+        &self,
+        text: &str,
+        media: &[Media],
+        key: &str,
+    ) -> Result<String, BotError>;
+
+    /// Publishes `text` in the thread of the post `in_reply_to`, keyed like [`Self::post`].
+    async fn reply(&self, in_reply_to: &str, text: &str, key: &str) -> Result<String, BotError>;
+
+    async fn delete(&self, id: &str) -> Result<(), BotError>;
+}
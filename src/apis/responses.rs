@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use reqwest::{header::HeaderMap, Response, StatusCode};
+
+use crate::error::PublishError;
+
+/// How a network reports failed requests.
+pub struct Responses {
+    /// When the rate limit resets, from the headers of a rejected request.
+    pub reset: fn(&HeaderMap) -> Option<DateTime<Utc>>,
+    /// Failures the network reports its own way, e.g. duplicates or expired tokens; `None`
+    /// leaves the response to the common classification.
+    pub classify: fn(StatusCode, &str) -> Option<PublishError>,
+}
+
+impl Responses {
+    /// Passes successful responses through and classifies the rest.
+    pub async fn check(&self, response: Response) -> Result<Response, PublishError> {
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let reset = (self.reset)(response.headers());
+        let body = response.text().await.unwrap_or_default();
+
+        Err(match (self.classify)(status, &body) {
+            Some(error) => error,
+            None => match status {
+                StatusCode::UNAUTHORIZED => PublishError::Unauthorized,
+                StatusCode::TOO_MANY_REQUESTS => PublishError::RateLimited { reset },
+                _ => PublishError::Other { status, body },
+            },
+        })
+    }
+}
+
+/// Reads a reset given in seconds since the epoch.
+pub fn timestamp_reset(headers: &HeaderMap, name: &str) -> Option<DateTime<Utc>> {
+    let reset = headers.get(name)?.to_str().ok()?.parse().ok()?;
+    DateTime::from_timestamp(reset, 0)
+}
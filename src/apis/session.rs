@@ -0,0 +1,146 @@
+use std::{future::Future, sync::Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use shuttle_runtime::async_trait;
+use sqlx::PgPool;
+
+use crate::{
+    error::{BotError, PublishError},
+    types::{AuthProvider, BotType, Session},
+};
+
+/// Refresh access tokens this long before the network expires them.
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::minutes(1);
+
+/// Tokens a network issued in exchange for a stale session.
+#[derive(Debug)]
+pub struct Tokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// How a network trades a session for new tokens.
+#[async_trait]
+pub trait TokenExchange: Sync {
+    async fn exchange(&self, session: Session) -> Result<Tokens, BotError>;
+}
+
+/// A bot's session with a network whose access tokens expire, shared by the requests of a job.
+#[derive(Debug)]
+pub struct SessionTokens {
+    provider: AuthProvider,
+    bot_type: BotType,
+    pool: PgPool,
+    access_token: Mutex<String>,
+}
+
+impl SessionTokens {
+    /// Loads the bot's stored session, failing with [`BotError::NotAuthorized`] until the bot
+    /// has been onboarded.
+    pub async fn load(
+        provider: AuthProvider,
+        bot_type: BotType,
+        pool: &PgPool,
+    ) -> Result<(Self, Session), BotError> {
+        let session: Session = sqlx::query_as(
+            "SELECT * FROM Sessions WHERE Sessions.provider = $1 AND Sessions.bot_type = $2;",
+        )
+        .bind(&provider)
+        .bind(&bot_type)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| BotError::NotAuthorized(bot_type.clone(), provider.clone()))?;
+
+        let tokens = Self {
+            provider,
+            bot_type,
+            pool: pool.clone(),
+            access_token: Mutex::new(session.access_token.clone()),
+        };
+
+        Ok((tokens, session))
+    }
+
+    /// Refreshes the loaded `session` if its access token is about to expire.
+    pub async fn refresh_if_expired(
+        &self,
+        exchange: &impl TokenExchange,
+        session: &Session,
+    ) -> Result<(), BotError> {
+        if session.is_expired(TOKEN_EXPIRY_MARGIN) {
+            self.refresh(exchange, &session.access_token).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the stale access token with ones the network exchanged the session for.
+    ///
+    /// The session row stays locked until the new tokens are committed, so concurrent jobs of
+    /// the same bot never refresh with an already consumed refresh token.
+    async fn refresh(
+        &self,
+        exchange: &impl TokenExchange,
+        stale_access_token: &str,
+    ) -> Result<(), BotError> {
+        let mut tx = self.pool.begin().await?;
+
+        let session: Session = sqlx::query_as(
+            "SELECT * FROM Sessions WHERE Sessions.provider = $1 AND Sessions.bot_type = $2 FOR UPDATE;",
+        )
+        .bind(&self.provider)
+        .bind(&self.bot_type)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| BotError::Auth(self.provider.clone(), "session was removed".to_string()))?;
+
+        // Another job refreshed the tokens while we were waiting for the lock.
+        if session.access_token != stale_access_token && !session.is_expired(TOKEN_EXPIRY_MARGIN) {
+            tx.commit().await?;
+            *self.access_token.lock().unwrap() = session.access_token;
+            return Ok(());
+        }
+
+        let tokens = exchange.exchange(session).await?;
+
+        sqlx::query("UPDATE Sessions SET access_token = $1, refresh_token = $2, expires_at = $3 WHERE provider = $4 AND bot_type = $5;")
+            .bind(&tokens.access_token)
+            .bind(&tokens.refresh_token)
+            .bind(tokens.expires_at)
+            .bind(&self.provider)
+            .bind(&self.bot_type)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        *self.access_token.lock().unwrap() = tokens.access_token;
+
+        Ok(())
+    }
+
+    /// Runs `request` with the current access token, refreshing a rejected token once.
+    pub async fn authorized<T, F, Fut>(
+        &self,
+        exchange: &impl TokenExchange,
+        request: F,
+    ) -> Result<T, BotError>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<T, PublishError>>,
+    {
+        let access_token = self.access_token.lock().unwrap().clone();
+        let publish_error = |e| BotError::Publish(self.provider.clone(), e);
+
+        match request(access_token.clone()).await {
+            Err(PublishError::Unauthorized) => {
+                self.refresh(exchange, &access_token).await?;
+
+                let access_token = self.access_token.lock().unwrap().clone();
+                request(access_token).await.map_err(publish_error)
+            }
+            result => result.map_err(publish_error),
+        }
+    }
+}
@@ -0,0 +1,593 @@
+use std::str::FromStr;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{header::RETRY_AFTER, Client, Url};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use shuttle_runtime::{async_trait, SecretStore};
+
+use super::{responses::Responses, Media, Publisher};
+use crate::{
+    error::{BotError, PublishError},
+    types::{AuthProvider, BotType, Flight, Post},
+};
+
+/// Body layout a webhook expects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WebhookFormat {
+    /// The announcement's flight fields and rendered text.
+    Json,
+    /// Discord embed.
+    Discord,
+    /// Slack Block Kit message.
+    Slack,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebhookTarget {
+    pub url: String,
+    pub format: WebhookFormat,
+}
+
+impl WebhookTarget {
+    /// Names the webhook in the outbox and in errors. Whoever has the URL can post to the
+    /// channel, so it is kept out of both.
+    pub fn key(&self) -> String {
+        hex::encode(Sha256::digest(self.url.as_bytes()))[..16].to_string()
+    }
+}
+
+impl FromStr for WebhookTarget {
+    type Err = BotError;
+
+    /// Parses `[discord:|slack:|json:]{url}`.
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        let (format, url) = match target.split_once(':') {
+            Some(("discord", url)) => (WebhookFormat::Discord, url),
+            Some(("slack", url)) => (WebhookFormat::Slack, url),
+            Some(("json", url)) => (WebhookFormat::Json, url),
+            _ => (WebhookFormat::Json, target),
+        };
+
+        Url::parse(url)
+            .map_err(|e| BotError::Config(format!("invalid webhook URL `{}`: {}", url, e)))?;
+
+        Ok(Self {
+            url: url.to_string(),
+            format,
+        })
+    }
+}
+
+/// The chat webhooks a bot announces on.
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    pub targets: Vec<WebhookTarget>,
+    /// Key the payloads are signed with, if the receivers verify them.
+    pub secret: Option<String>,
+}
+
+impl WebhookConfig {
+    /// Reads the optional `WEBHOOK_{key}_URLS` secret, a comma separated list of
+    /// `[discord:|slack:|json:]{url}`, and the `WEBHOOK_{key}_SECRET` signing key.
+    pub fn from_secrets(secrets: &SecretStore, key: &str) -> Result<Option<Self>, BotError> {
+        let Some(urls) = secrets.get(&format!("WEBHOOK_{}_URLS", key)) else {
+            return Ok(None);
+        };
+
+        let targets = urls
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if targets.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            targets,
+            secret: secrets.get(&format!("WEBHOOK_{}_SECRET", key)),
+        }))
+    }
+}
+
+/// Posts announcements to chat webhooks.
+#[derive(Debug)]
+pub struct WebhookPublisher {
+    pub client: Client,
+    bot_type: BotType,
+    config: WebhookConfig,
+}
+
+impl WebhookPublisher {
+    pub fn new(config: &WebhookConfig, bot_type: BotType) -> Self {
+        Self {
+            client: Client::new(),
+            bot_type,
+            config: config.clone(),
+        }
+    }
+
+    /// Sends a payload in the webhook's format. Failures aren't retried here, the announcement
+    /// backs off in the outbox like on any other network.
+    async fn send(
+        &self,
+        target: &WebhookTarget,
+        id: &str,
+        text: &str,
+        flight: Option<&Flight>,
+    ) -> Result<(), BotError> {
+        let body = match target.format {
+            WebhookFormat::Json => json_payload(&self.bot_type, id, text, flight),
+            WebhookFormat::Discord => discord_payload(text, flight),
+            WebhookFormat::Slack => slack_payload(text, flight),
+        };
+
+        self.send_once(target, id, &body.to_string())
+            .await
+            .map_err(|e| BotError::Publish(AuthProvider::WEBHOOK, e))
+    }
+
+    async fn send_once(
+        &self,
+        target: &WebhookTarget,
+        id: &str,
+        body: &str,
+    ) -> Result<(), PublishError> {
+        let mut request = self
+            .client
+            .post(&target.url)
+            .header("content-type", "application/json")
+            .header("x-announcement-id", id)
+            .body(body.to_string());
+
+        if let Some(secret) = &self.config.secret {
+            let timestamp = Utc::now().timestamp().to_string();
+            request = request
+                .header("x-webhook-timestamp", &timestamp)
+                .header("x-webhook-signature", sign(secret, &timestamp, body));
+        }
+
+        // Request errors name the URL, and they end up in the outbox.
+        let response = request.send().await.map_err(reqwest::Error::without_url)?;
+        RESPONSES.check(response).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Publisher for WebhookPublisher {
+    fn provider(&self) -> AuthProvider {
+        AuthProvider::WEBHOOK
+    }
+
+    /// Sends the flight along with the text to the row's webhook, keyed by the announcement so
+    /// receivers can drop a payload they got before.
+    async fn announce(&self, post: &Post) -> Result<String, BotError> {
+        let id = post.announcement_id.to_string();
+        let key = post
+            .target
+            .as_deref()
+            .ok_or_else(|| BotError::Config("webhook announcement has no target".to_string()))?;
+        let target = self
+            .config
+            .targets
+            .iter()
+            .find(|target| target.key() == key)
+            .ok_or_else(|| BotError::Config(format!("webhook {} is no longer configured", key)))?;
+
+        self.send(
+            target,
+            &id,
+            &post.text,
+            post.flight.as_ref().map(|flight| &flight.0),
+        )
+        .await?;
+        Ok(id)
+    }
+
+    /// Sends the text to the bot's webhook. Without an announcement row there's no telling
+    /// which one is meant, so it fails if there are several.
+    async fn post(&self, text: &str, key: &str) -> Result<String, BotError> {
+        let [target] = self.config.targets.as_slice() else {
+            return Err(BotError::Config(format!(
+                "{} webhooks are configured, posting needs a single one",
+                self.config.targets.len()
+            )));
+        };
+
+        self.send(target, key, text, None).await?;
+        Ok(key.to_string())
+    }
+
+    async fn post_with_media(
+        &self,
+        _text: &str,
+        _media: &[Media],
+        _key: &str,
+    ) -> Result<String, BotError> {
+        Err(BotError::Publish(
+            AuthProvider::WEBHOOK,
+            PublishError::Unsupported("attaching media to a webhook message"),
+        ))
+    }
+
+    async fn reply(&self, _in_reply_to: &str, _text: &str, _key: &str) -> Result<String, BotError> {
+        Err(BotError::Publish(
+            AuthProvider::WEBHOOK,
+            PublishError::Unsupported("replying to a webhook message"),
+        ))
+    }
+
+    async fn delete(&self, _id: &str) -> Result<(), BotError> {
+        Err(BotError::Publish(
+            AuthProvider::WEBHOOK,
+            PublishError::Unsupported("deleting a webhook message"),
+        ))
+    }
+}
+
+/// `sha256={hex}` HMAC of `{timestamp}.{body}`; the timestamp lets receivers reject replays.
+fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn json_payload(bot_type: &BotType, id: &str, text: &str, flight: Option<&Flight>) -> Value {
+    json!({
+        "announcement_id": id,
+        "ranking": bot_type,
+        "text": text,
+        "flight": flight.map(|flight| json!({
+            "ident": flight.ident,
+            "fa_flight_id": flight.fa_flight_id,
+            "altitude_ft": flight.altitude.map(|altitude| altitude * 100),
+            "groundspeed_kts": flight.groundspeed,
+            "origin": flight.origin,
+            "destination": flight.destination,
+            "aircraft_type": flight.aircraft_type,
+            "position_time": flight.position_time,
+            "url": flight.flightaware_url(),
+        })),
+    })
+}
+
+/// Label and value of the flight fields shown in chat messages.
+fn fields(flight: &Flight) -> Vec<(&'static str, String)> {
+    let or_unknown = |value: &Option<String>| value.clone().unwrap_or("Unknown".to_string());
+
+    vec![
+        (
+            "Altitude",
+            flight.altitude.map_or("N/A".to_string(), |altitude| {
+                format!("{} ft", altitude * 100)
+            }),
+        ),
+        (
+            "Groundspeed",
+            flight
+                .groundspeed
+                .map_or("N/A".to_string(), |speed| format!("{} kts", speed)),
+        ),
+        ("Origin", or_unknown(&flight.origin)),
+        ("Destination", or_unknown(&flight.destination)),
+    ]
+}
+
+fn discord_payload(text: &str, flight: Option<&Flight>) -> Value {
+    let Some(flight) = flight else {
+        return json!({ "content": text });
+    };
+
+    let fields: Vec<Value> = fields(flight)
+        .into_iter()
+        .map(|(name, value)| json!({ "name": name, "value": value, "inline": true }))
+        .collect();
+
+    json!({
+        "embeds": [{
+            "title": flight.ident,
+            "url": flight.flightaware_url(),
+            "description": text,
+            "fields": fields,
+            "timestamp": flight.position_time.map(|time| time.to_rfc3339()),
+        }],
+    })
+}
+
+fn slack_payload(text: &str, flight: Option<&Flight>) -> Value {
+    let Some(flight) = flight else {
+        return json!({ "text": text });
+    };
+
+    let fields: Vec<Value> = fields(flight)
+        .into_iter()
+        .map(|(name, value)| json!({ "type": "mrkdwn", "text": format!("*{}*\n{}", name, value) }))
+        .collect();
+
+    json!({
+        // Shown in notifications, where blocks aren't rendered.
+        "text": text,
+        "blocks": [
+            {
+                "type": "header",
+                "text": { "type": "plain_text", "text": flight.ident },
+            },
+            { "type": "section", "fields": fields },
+            {
+                "type": "section",
+                "text": {
+                    "type": "mrkdwn",
+                    "text": format!("<{}|More info on FlightAware>", flight.flightaware_url()),
+                },
+            },
+        ],
+    })
+}
+
+/// Chat services say when to try again in seconds.
+const RESPONSES: Responses = Responses {
+    reset: |headers| {
+        let seconds = headers
+            .get(RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .parse::<f64>()
+            .ok()?;
+        Some(Utc::now() + chrono::Duration::try_milliseconds((seconds * 1000.0) as i64)?)
+    },
+    classify: |_, _| None,
+};
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use shuttle_runtime::tokio;
+    use sqlx::types::Json;
+    use warp::http::StatusCode;
+
+    use super::*;
+    use crate::{test_support::MockWebhook, types::PostStatus};
+
+    fn flight() -> Flight {
+        Flight {
+            ident: "N650GD".to_string(),
+            fa_flight_id: Some("N650GD-1717160000-adhoc-0001".to_string()),
+            ranking: BotType::ALTITUDE,
+            altitude: Some(510),
+            groundspeed: Some(420),
+            origin: Some("Teterboro [KTEB]".to_string()),
+            destination: None,
+            aircraft_type: Some("GA6C".to_string()),
+            position_time: DateTime::from_timestamp(1717160000, 0),
+        }
+    }
+
+    fn post() -> Post {
+        Post {
+            id: 7,
+            bot_type: BotType::ALTITUDE,
+            ident: "N650GD".to_string(),
+            fa_flight_id: None,
+            provider: Some(AuthProvider::WEBHOOK),
+            target: None,
+            announcement_id: 3,
+            external_id: None,
+            text: "Current highest flight: N650GD".to_string(),
+            flight: Some(Json(flight())),
+            track: None,
+            status: PostStatus::PENDING,
+            error: None,
+            posted_at: Utc::now(),
+            attempts: 0,
+            next_attempt_at: None,
+            delivered_at: None,
+        }
+    }
+
+    fn publisher(targets: Vec<WebhookTarget>, secret: Option<&str>) -> WebhookPublisher {
+        let config = WebhookConfig {
+            targets,
+            secret: secret.map(str::to_string),
+        };
+
+        WebhookPublisher::new(&config, BotType::ALTITUDE)
+    }
+
+    #[test]
+    fn parses_targets() {
+        assert_eq!(
+            "discord:https://discord.com/api/webhooks/1/abc"
+                .parse::<WebhookTarget>()
+                .unwrap(),
+            WebhookTarget {
+                url: "https://discord.com/api/webhooks/1/abc".to_string(),
+                format: WebhookFormat::Discord,
+            }
+        );
+        assert_eq!(
+            "https://example.com/hook"
+                .parse::<WebhookTarget>()
+                .unwrap()
+                .format,
+            WebhookFormat::Json
+        );
+        assert!("slack:not a url".parse::<WebhookTarget>().is_err());
+    }
+
+    #[test]
+    fn formats_discord_embed() {
+        let payload = discord_payload("Current highest flight: N650GD", Some(&flight()));
+        let embed = &payload["embeds"][0];
+
+        assert_eq!(embed["title"], "N650GD");
+        assert_eq!(
+            embed["url"],
+            "https://www.flightaware.com/live/flight/id/N650GD-1717160000-adhoc-0001"
+        );
+        assert_eq!(embed["fields"][0]["value"], "51000 ft");
+        assert_eq!(embed["fields"][3]["value"], "Unknown");
+    }
+
+    #[test]
+    fn formats_slack_blocks() {
+        let payload = slack_payload("Current highest flight: N650GD", Some(&flight()));
+
+        assert_eq!(payload["text"], "Current highest flight: N650GD");
+        assert_eq!(payload["blocks"][0]["text"]["text"], "N650GD");
+        assert_eq!(
+            payload["blocks"][1]["fields"][1]["text"],
+            "*Groundspeed*\n420 kts"
+        );
+    }
+
+    #[test]
+    fn signs_timestamp_and_body() {
+        // echo -n '1717160000.{}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", "1717160000", "{}"),
+            "sha256=2427be03c87d25d5126af79a0daba61cdf4e27df4aae1bd5086395d3902d09cc"
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_signed_payload_in_the_webhooks_format() {
+        let webhook = MockWebhook::start();
+        let targets = vec![
+            webhook.target("json", WebhookFormat::Json),
+            webhook.target("discord", WebhookFormat::Discord),
+        ];
+        let publisher = publisher(targets.clone(), Some("secret"));
+
+        for target in &targets {
+            let post = Post {
+                target: Some(target.key()),
+                ..post()
+            };
+            assert_eq!(publisher.announce(&post).await.unwrap(), "3");
+        }
+
+        let requests = webhook.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].body["flight"]["altitude_ft"], 51000);
+        assert_eq!(requests[0].body["announcement_id"], "3");
+        assert_eq!(requests[1].body["embeds"][0]["title"], "N650GD");
+
+        let timestamp = requests[0].timestamp.as_deref().unwrap();
+        assert!(timestamp.parse::<i64>().is_ok());
+        assert_eq!(
+            requests[0].signature.as_deref(),
+            Some(sign("secret", timestamp, &requests[0].raw_body).as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_to_the_rows_webhook_only() {
+        let webhook = MockWebhook::start();
+        let json = webhook.target("json", WebhookFormat::Json);
+        let publisher = publisher(
+            vec![json.clone(), webhook.target("slack", WebhookFormat::Slack)],
+            None,
+        );
+        let post = Post {
+            target: Some(json.key()),
+            ..post()
+        };
+
+        publisher.announce(&post).await.unwrap();
+
+        let requests = webhook.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/json");
+        assert_eq!(requests[0].signature, None);
+    }
+
+    #[tokio::test]
+    async fn leaves_retrying_to_the_outbox() {
+        let webhook = MockWebhook::start();
+        let json = webhook.target("json", WebhookFormat::Json);
+        let publisher = publisher(vec![json.clone()], None);
+        let post = Post {
+            target: Some(json.key()),
+            ..post()
+        };
+        webhook.fail_next(StatusCode::TOO_MANY_REQUESTS);
+
+        assert!(matches!(
+            publisher.announce(&post).await,
+            Err(BotError::Publish(
+                AuthProvider::WEBHOOK,
+                PublishError::RateLimited { reset: Some(_) }
+            ))
+        ));
+        assert_eq!(webhook.attempts(), 1);
+    }
+
+    #[tokio::test]
+    async fn fails_for_webhook_no_longer_configured() {
+        let webhook = MockWebhook::start();
+        let publisher = publisher(vec![webhook.target("json", WebhookFormat::Json)], None);
+        let removed = webhook.target("removed", WebhookFormat::Json);
+
+        for target in [None, Some(removed.key())] {
+            let post = Post { target, ..post() };
+            let error = publisher.announce(&post).await.unwrap_err();
+
+            assert!(matches!(error, BotError::Config(_)));
+            assert!(!error.to_string().contains(&webhook.url));
+        }
+        assert_eq!(webhook.attempts(), 0);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_url_out_of_request_errors() {
+        // Nothing listens on the discard port.
+        let target: WebhookTarget = "http://127.0.0.1:9/hooks/token".parse().unwrap();
+        let publisher = publisher(vec![target.clone()], None);
+        let post = Post {
+            target: Some(target.key()),
+            ..post()
+        };
+
+        let error = publisher.announce(&post).await.unwrap_err();
+
+        assert!(matches!(
+            error,
+            BotError::Publish(AuthProvider::WEBHOOK, PublishError::Request(_))
+        ));
+        assert!(!error.to_string().contains("token"));
+    }
+
+    #[tokio::test]
+    async fn posts_only_to_a_single_webhook() {
+        let webhook = MockWebhook::start();
+        let json = webhook.target("json", WebhookFormat::Json);
+
+        publisher(vec![json.clone()], None)
+            .post("Testing", "7")
+            .await
+            .unwrap();
+        assert!(matches!(
+            publisher(
+                vec![json, webhook.target("slack", WebhookFormat::Slack)],
+                None
+            )
+            .post("Testing", "8")
+            .await,
+            Err(BotError::Config(_))
+        ));
+
+        let requests = webhook.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].body["text"], "Testing");
+        assert_eq!(requests[0].body["announcement_id"], "7");
+    }
+}
@@ -1,180 +1,556 @@
-use shuttle_runtime::tokio::io::{AsyncBufReadExt, AsyncWriteExt};
-use shuttle_runtime::tokio::sync::mpsc;
-use shuttle_runtime::tokio::task;
-use shuttle_runtime::tokio::{io::BufReader, net::TcpListener};
+use std::time::Duration as StdDuration;
 
+use chrono::{DateTime, Duration, Utc};
 use oauth2::{
-    basic::BasicClient, reqwest::async_http_client, AuthUrl, AuthorizationCode, ClientId,
-    ClientSecret, CsrfToken, PkceCodeChallenge, RedirectUrl, RefreshToken, Scope, TokenResponse,
-    TokenUrl,
+    basic::{BasicClient, BasicTokenResponse},
+    reqwest::async_http_client,
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, RefreshToken, Scope, TokenResponse, TokenUrl,
 };
-use reqwest::{Client, Url};
-use serde_json::json;
+use reqwest::{
+    multipart::{Form, Part},
+    Client, StatusCode, Url,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shuttle_runtime::{async_trait, tokio, SecretStore};
 use sqlx::PgPool;
 
-use crate::types::{AuthProvider, BotType, Session};
+use super::{
+    responses::{timestamp_reset, Responses},
+    session::{SessionTokens, TokenExchange, Tokens},
+    Media, Publisher,
+};
+use crate::{
+    error::{require_secret, BotError, PublishError},
+    map::track_map,
+    types::{AuthProvider, BotType, Post, Session},
+};
+
+/// Bytes sent per `APPEND` of a chunked media upload.
+const MEDIA_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Status checks of an upload X is still processing before giving up on it.
+const MEDIA_STATUS_POLLS: usize = 10;
+
+/// X endpoints the bots talk to.
+#[derive(Clone, Debug)]
+pub struct XEndpoints {
+    pub api_url: String,
+    pub auth_url: String,
+    pub token_url: String,
+    /// Where X sends the account owner back to, i.e. this service's `/callback`.
+    pub redirect_url: String,
+}
+
+impl Default for XEndpoints {
+    fn default() -> Self {
+        Self {
+            api_url: "https://api.twitter.com/2".to_string(),
+            auth_url: "https://twitter.com/i/oauth2/authorize".to_string(),
+            token_url: "https://api.twitter.com/2/oauth2/token".to_string(),
+            redirect_url: "http://twitter-aviation-bots.shuttleapp.rs/callback".to_string(),
+        }
+    }
+}
+
+/// OAuth2 client credentials of a bot's X app.
+#[derive(Clone, Debug)]
+pub struct XCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+    pub endpoints: XEndpoints,
+}
+
+impl XCredentials {
+    /// Reads the `X_{key}_CLIENT_ID` and `X_{key}_CLIENT_SECRET` secrets.
+    pub fn from_secrets(
+        secrets: &SecretStore,
+        key: &str,
+        endpoints: XEndpoints,
+    ) -> Result<Self, BotError> {
+        Ok(Self {
+            client_id: require_secret(secrets, &format!("X_{}_CLIENT_ID", key))?,
+            client_secret: require_secret(secrets, &format!("X_{}_CLIENT_SECRET", key))?,
+            endpoints,
+        })
+    }
+
+    fn oauth_client(&self) -> Result<BasicClient, BotError> {
+        let auth_url = AuthUrl::new(self.endpoints.auth_url.clone())
+            .map_err(|e| BotError::Config(format!("invalid X authorization endpoint: {}", e)))?;
+        let token_url = TokenUrl::new(self.endpoints.token_url.clone())
+            .map_err(|e| BotError::Config(format!("invalid X token endpoint: {}", e)))?;
+        let redirect_url = RedirectUrl::new(self.endpoints.redirect_url.clone())
+            .map_err(|e| BotError::Config(format!("invalid X redirect URL: {}", e)))?;
+
+        Ok(BasicClient::new(
+            ClientId::new(self.client_id.clone()),
+            Some(ClientSecret::new(self.client_secret.clone())),
+            auth_url,
+            Some(token_url),
+        )
+        .set_redirect_uri(redirect_url))
+    }
+}
 
 #[derive(Debug)]
 pub struct XApi {
     pub url: String,
     pub client: Client,
-    pub access_token: String,
+    auth_client: BasicClient,
+    bot_type: BotType,
+    session: SessionTokens,
 }
 
 impl XApi {
-    pub async fn new_and_authorize(
-        client_id: String,
-        client_secret: String,
+    /// Opens the bot's stored X session, refreshing its access token if needed.
+    ///
+    /// Fails with [`BotError::NotAuthorized`] until the bot has been onboarded via `/auth/{bot}/start`.
+    pub async fn new(
+        credentials: &XCredentials,
         bot_type: BotType,
         pool: &PgPool,
-    ) -> Self {
-        let url = "https://api.twitter.com/2".to_string();
-
-        let client_id = ClientId::new(client_id);
-        let client_secret = ClientSecret::new(client_secret);
-
-        let auth_url = AuthUrl::new("https://twitter.com/i/oauth2/authorize".to_string())
-            .expect("Invalid authorization endpoint.");
-        let token_url = TokenUrl::new("https://api.twitter.com/2/oauth2/token".to_string())
-            .expect("Invalid token endpoint.");
-
-        let auth_client =
-            BasicClient::new(client_id, Some(client_secret), auth_url, Some(token_url))
-                .set_redirect_uri(
-                    RedirectUrl::new(
-                        "http://twitter-aviation-bots.shuttleapp.rs/callback".to_string(),
-                    )
-                    .expect("Invalid redirect URL"),
-                );
-
-        let sessions: Vec<Session> = sqlx::query_as(
-            "SELECT * FROM Sessions WHERE Sessions.provider = 'X' AND Sessions.bot_type = $1;",
-        )
-        .bind(&bot_type)
-        .fetch_all(pool)
-        .await
-        .unwrap();
-
-        if !sessions.is_empty() {
-            let session = sessions.first().unwrap();
-
-            let mut refresh_token = RefreshToken::new(session.refresh_token.clone());
-
-            let tokens = auth_client
-                .exchange_refresh_token(&refresh_token)
-                .request_async(async_http_client)
-                .await
-                .unwrap();
+    ) -> Result<Self, BotError> {
+        let (session, stored) =
+            SessionTokens::load(AuthProvider::X, bot_type.clone(), pool).await?;
 
-            let access_token = tokens.access_token().secret().to_string();
-            if let Some(new_refresh_token) = tokens.refresh_token() {
-                refresh_token = new_refresh_token.clone();
-            }
+        let x_api = Self {
+            url: credentials.endpoints.api_url.clone(),
+            client: Client::new(),
+            auth_client: credentials.oauth_client()?,
+            bot_type,
+            session,
+        };
+        x_api.session.refresh_if_expired(&x_api, &stored).await?;
 
-            sqlx::query("UPDATE Sessions SET access_token = $1, refresh_token = $2 WHERE provider = 'X' AND bot_type = $3;")
-            .bind(&access_token)
-            .bind(refresh_token.secret().to_string())
-            .bind(bot_type)
-            .execute(pool)
-            .await
-            .unwrap();
+        Ok(x_api)
+    }
 
-            return Self {
-                client: Client::new(),
-                access_token,
-                url,
-            };
-        }
+    /// Whether the bot has been onboarded, without touching its tokens.
+    pub async fn is_authorized(bot_type: &BotType, pool: &PgPool) -> Result<bool, BotError> {
+        Ok(sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM Sessions WHERE Sessions.provider = 'X' AND Sessions.bot_type = $1);",
+        )
+        .bind(bot_type)
+        .fetch_one(pool)
+        .await?)
+    }
 
+    /// Builds the URL a bot account owner has to visit to grant the bot access.
+    ///
+    /// The returned state and PKCE verifier are needed to complete the authorization.
+    pub fn authorize_url(
+        credentials: &XCredentials,
+    ) -> Result<(Url, CsrfToken, PkceCodeVerifier), BotError> {
         let (pkce_code_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
 
-        let (auth_url, _) = auth_client
+        let (auth_url, state) = credentials
+            .oauth_client()?
             .authorize_url(CsrfToken::new_random)
             .add_scope(Scope::new("users.read".to_string()))
             .add_scope(Scope::new("tweet.read".to_string()))
             .add_scope(Scope::new("tweet.write".to_string()))
+            .add_scope(Scope::new("media.write".to_string()))
             .add_scope(Scope::new("offline.access".to_string()))
             .set_pkce_challenge(pkce_code_challenge)
             .url();
 
-        println!("[{:?}] Browse to: {}", bot_type, auth_url);
+        Ok((auth_url, state, pkce_verifier))
+    }
 
-        let (tx, mut rx) = mpsc::channel(1);
+    /// Exchanges the code X redirected back with for tokens and stores them as the bot's session.
+    pub async fn authorize(
+        credentials: &XCredentials,
+        bot_type: BotType,
+        code: AuthorizationCode,
+        pkce_verifier: PkceCodeVerifier,
+        pool: &PgPool,
+    ) -> Result<(), BotError> {
+        let tokens = credentials
+            .oauth_client()?
+            .exchange_code(code)
+            .set_pkce_verifier(pkce_verifier)
+            .request_async(async_http_client)
+            .await
+            .map_err(|e| {
+                BotError::Auth(
+                    AuthProvider::X,
+                    format!("exchanging the authorization code: {}", e),
+                )
+            })?;
 
-        task::spawn(async move {
-            let listener = TcpListener::bind("0.0.0.0:8000").await.unwrap();
+        let access_token = tokens.access_token().secret().to_string();
+        let refresh_token = tokens
+            .refresh_token()
+            .ok_or_else(|| BotError::Auth(AuthProvider::X, "no refresh token granted".to_string()))?
+            .secret()
+            .to_string();
+
+        sqlx::query("INSERT INTO Sessions (provider, bot_type, access_token, refresh_token, expires_at) VALUES ($1, $2, $3, $4, $5) \
+            ON CONFLICT (provider, bot_type) DO UPDATE SET access_token = EXCLUDED.access_token, refresh_token = EXCLUDED.refresh_token, expires_at = EXCLUDED.expires_at;")
+            .bind(AuthProvider::X)
+            .bind(bot_type)
+            .bind(&access_token)
+            .bind(&refresh_token)
+            .bind(expires_at(&tokens))
+            .execute(pool)
+            .await?;
 
-            if let Ok((mut stream, _)) = listener.accept().await {
-                let mut reader = BufReader::new(&mut stream);
+        Ok(())
+    }
 
-                let mut request_line = String::new();
-                reader.read_line(&mut request_line).await.unwrap();
+    async fn create_tweet(&self, body: Value) -> Result<String, BotError> {
+        self.session
+            .authorized(self, |access_token| self.send_tweet(access_token, &body))
+            .await
+    }
 
-                if request_line.starts_with("GET /callback") {
-                    let redirect_url = request_line.split_whitespace().nth(1).unwrap();
-                    let url = Url::parse(&("http://localhost".to_string() + redirect_url)).unwrap();
+    async fn send_tweet(&self, access_token: String, body: &Value) -> Result<String, PublishError> {
+        let response = self
+            .client
+            .post(format!("{}/tweets", &self.url))
+            .bearer_auth(access_token)
+            .json(body)
+            .send()
+            .await?;
 
-                    let code = url
-                        .query_pairs()
-                        .find(|(key, _)| key == "code")
-                        .map(|(_, code)| AuthorizationCode::new(code.into_owned()))
-                        .expect("Code not found.");
+        let created = RESPONSES.check(response).await?.json::<Created>().await?;
+        Ok(created.data.id)
+    }
 
-                    let state = url
-                        .query_pairs()
-                        .find(|(key, _)| key == "state")
-                        .map(|(_, state)| CsrfToken::new(state.into_owned()))
-                        .expect("State not found.");
+    #[allow(dead_code)]
+    async fn send_delete(&self, access_token: String, id: &str) -> Result<(), PublishError> {
+        let response = self
+            .client
+            .delete(format!("{}/tweets/{}", &self.url, id))
+            .bearer_auth(access_token)
+            .send()
+            .await?;
+
+        RESPONSES.check(response).await?;
+        Ok(())
+    }
 
-                    let message = "DONE";
-                    let response = format!(
-                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
-                        message.len(),
-                        message
-                    );
-                    stream.write_all(response.as_bytes()).await.unwrap();
+    /// Uploads an image in chunks and returns its media id once X has processed it.
+    async fn upload_media(
+        &self,
+        access_token: String,
+        media: &Media,
+    ) -> Result<String, PublishError> {
+        let total_bytes = media.bytes.len().to_string();
+        let init = self
+            .media_command(
+                &access_token,
+                &[
+                    ("command", "INIT"),
+                    ("total_bytes", &total_bytes),
+                    ("media_type", &media.mime_type),
+                    ("media_category", "tweet_image"),
+                ],
+            )
+            .await?;
+        let media_id = init.data.id;
+
+        for (segment_index, chunk) in media.bytes.chunks(MEDIA_CHUNK_SIZE).enumerate() {
+            let part = Part::bytes(chunk.to_vec())
+                .file_name("media")
+                .mime_str("application/octet-stream")?;
+            let form = Form::new()
+                .text("command", "APPEND")
+                .text("media_id", media_id.clone())
+                .text("segment_index", segment_index.to_string())
+                .part("media", part);
+
+            let response = self
+                .client
+                .post(format!("{}/media/upload", &self.url))
+                .bearer_auth(&access_token)
+                .multipart(form)
+                .send()
+                .await?;
+
+            RESPONSES.check(response).await?;
+        }
 
-                    tx.send((code, state)).await.unwrap();
+        let mut uploaded = self
+            .media_command(
+                &access_token,
+                &[("command", "FINALIZE"), ("media_id", &media_id)],
+            )
+            .await?;
+
+        for _ in 0..MEDIA_STATUS_POLLS {
+            let Some(processing) = &uploaded.data.processing_info else {
+                break;
+            };
+
+            match processing.state.as_str() {
+                "succeeded" => break,
+                "failed" => {
+                    return Err(PublishError::MediaProcessing(
+                        processing
+                            .error
+                            .as_ref()
+                            .map_or("unknown error".to_string(), |e| e.to_string()),
+                    ))
                 }
+                _ => {}
             }
-        });
 
-        let (code, _state) = rx.recv().await.unwrap();
+            tokio::time::sleep(StdDuration::from_secs(
+                processing.check_after_secs.unwrap_or(1),
+            ))
+            .await;
 
-        let tokens = auth_client
-            .exchange_code(code)
-            .set_pkce_verifier(pkce_verifier)
+            let response = self
+                .client
+                .get(format!("{}/media/upload", &self.url))
+                .bearer_auth(&access_token)
+                .query(&[("command", "STATUS"), ("media_id", &media_id)])
+                .send()
+                .await?;
+
+            uploaded = RESPONSES.check(response).await?.json().await?;
+        }
+
+        if let Some(alt_text) = &media.alt_text {
+            let response = self
+                .client
+                .post(format!("{}/media/metadata", &self.url))
+                .bearer_auth(&access_token)
+                .json(&json!({ "id": media_id, "metadata": { "alt_text": { "text": alt_text } } }))
+                .send()
+                .await?;
+
+            RESPONSES.check(response).await?;
+        }
+
+        Ok(media_id)
+    }
+
+    /// Sends an `INIT` or `FINALIZE` command of a chunked upload.
+    async fn media_command(
+        &self,
+        access_token: &str,
+        form: &[(&str, &str)],
+    ) -> Result<Uploaded, PublishError> {
+        let response = self
+            .client
+            .post(format!("{}/media/upload", &self.url))
+            .bearer_auth(access_token)
+            .form(form)
+            .send()
+            .await?;
+
+        Ok(RESPONSES.check(response).await?.json().await?)
+    }
+
+    /// Draws the map of the announced flight's track, if one was fetched.
+    fn track_map(&self, post: &Post) -> Option<Media> {
+        let flight = &post.flight.as_ref()?.0;
+        let track = &post.track.as_ref()?.0;
+
+        match track_map(flight, track) {
+            Ok(media) => Some(media),
+            Err(e) => {
+                eprintln!(
+                    "[{:?}] Couldn't render the map of {}, tweeting text only: {}",
+                    self.bot_type, flight.ident, e
+                );
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TokenExchange for XApi {
+    /// Trades the refresh token for new tokens; X rotates the refresh token on the way.
+    async fn exchange(&self, session: Session) -> Result<Tokens, BotError> {
+        let stored_refresh_token = session.refresh_token.ok_or_else(|| {
+            BotError::Auth(AuthProvider::X, "no refresh token stored".to_string())
+        })?;
+
+        let tokens = self
+            .auth_client
+            .exchange_refresh_token(&RefreshToken::new(stored_refresh_token.clone()))
             .request_async(async_http_client)
             .await
-            .unwrap();
+            .map_err(|e| {
+                BotError::Auth(
+                    AuthProvider::X,
+                    format!("refreshing the access token: {}", e),
+                )
+            })?;
+
+        Ok(Tokens {
+            access_token: tokens.access_token().secret().to_string(),
+            refresh_token: tokens
+                .refresh_token()
+                .map_or(stored_refresh_token, |token| token.secret().to_string()),
+            expires_at: expires_at(&tokens),
+        })
+    }
+}
 
-        let access_token = tokens.access_token().secret().to_string();
-        let refresh_token = tokens.refresh_token().unwrap().secret().to_string();
+#[async_trait]
+impl Publisher for XApi {
+    fn provider(&self) -> AuthProvider {
+        AuthProvider::X
+    }
 
-        sqlx::query("INSERT INTO Sessions (provider, bot_type, access_token, refresh_token) VALUES ($1, $2, $3, $4);")
-            .bind(AuthProvider::X)
-            .bind(bot_type)
-            .bind(&access_token)
-            .bind(&refresh_token)
-            .execute(pool)
-            .await
-            .unwrap();
+    /// Attaches a map of the flight's track when it can be drawn and uploaded. The
+    /// announcement matters more than its picture, so it goes out text-only otherwise.
+    async fn announce(&self, post: &Post) -> Result<String, BotError> {
+        let key = post.id.to_string();
+        let Some(map) = self.track_map(post) else {
+            return self.post(&post.text, &key).await;
+        };
+
+        match self.post_with_media(&post.text, &[map], &key).await {
+            Err(BotError::Publish(_, PublishError::MediaUpload(e))) => {
+                eprintln!(
+                    "[{:?}] Couldn't upload the map of {}, tweeting text only: {}",
+                    self.bot_type, post.ident, e
+                );
+                self.post(&post.text, &key).await
+            }
+            result => result,
+        }
+    }
 
-        Self {
-            client: Client::new(),
-            access_token,
-            url,
+    /// X has no idempotency keys, but rejects a repeated text as a duplicate.
+    async fn post(&self, text: &str, _key: &str) -> Result<String, BotError> {
+        self.create_tweet(json!({ "text": text })).await
+    }
+
+    async fn post_with_media(
+        &self,
+        text: &str,
+        media: &[Media],
+        _key: &str,
+    ) -> Result<String, BotError> {
+        let mut media_ids = vec![];
+
+        for media in media {
+            let media_id = self
+                .session
+                .authorized(self, |access_token| self.upload_media(access_token, media))
+                .await
+                .map_err(BotError::into_media_upload)?;
+            media_ids.push(media_id);
         }
+
+        self.create_tweet(json!({ "text": text, "media": { "media_ids": media_ids } }))
+            .await
     }
 
-    pub async fn tweet(&self, text: String) {
-        self.client
-            .post(format!("{}/tweets", &self.url))
-            .header("Content-Type", "application/json")
-            .bearer_auth(&self.access_token)
-            .body(json!({"text": text}).to_string())
-            .send()
+    async fn reply(&self, in_reply_to: &str, text: &str, _key: &str) -> Result<String, BotError> {
+        self.create_tweet(json!({ "text": text, "reply": { "in_reply_to_tweet_id": in_reply_to } }))
+            .await
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), BotError> {
+        self.session
+            .authorized(self, |access_token| self.send_delete(access_token, id))
+            .await
+    }
+}
+
+/// X rejects a repeated text as forbidden.
+const RESPONSES: Responses = Responses {
+    reset: |headers| timestamp_reset(headers, "x-rate-limit-reset"),
+    classify: |status, body| {
+        (status == StatusCode::FORBIDDEN && body.contains("duplicate content"))
+            .then_some(PublishError::Duplicate)
+    },
+};
+
+fn expires_at(tokens: &BasicTokenResponse) -> Option<DateTime<Utc>> {
+    let expires_in = Duration::from_std(tokens.expires_in()?).ok()?;
+    Some(Utc::now() + expires_in)
+}
+
+/// Response of the endpoint creating a tweet.
+#[derive(Debug, Deserialize)]
+struct Created {
+    data: CreatedData,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedData {
+    id: String,
+}
+
+/// Response of the media upload commands.
+#[derive(Debug, Deserialize)]
+struct Uploaded {
+    data: UploadedData,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadedData {
+    id: String,
+    processing_info: Option<ProcessingInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProcessingInfo {
+    /// `pending`, `in_progress`, `failed` or `succeeded`.
+    state: String,
+    check_after_secs: Option<u64>,
+    error: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_support::{insert_session, valid_expiry, MockX};
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn uploads_media_in_chunks(pool: PgPool) {
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        let x_api = XApi::new(&x.credentials(), BotType::ALTITUDE, &pool)
+            .await
+            .unwrap();
+        let bytes: Vec<u8> = (0..MEDIA_CHUNK_SIZE * 5 / 2).map(|i| i as u8).collect();
+        let media = Media {
+            bytes: bytes.clone(),
+            mime_type: "image/png".to_string(),
+            alt_text: None,
+        };
+
+        let id = x_api.post_with_media("Map", &[media], "1").await.unwrap();
+
+        assert_eq!(id, "1");
+        let uploaded = &x.tweet_media()[0][0];
+        assert_eq!(
+            uploaded.segments,
+            [MEDIA_CHUNK_SIZE, MEDIA_CHUNK_SIZE, MEDIA_CHUNK_SIZE / 2]
+        );
+        assert_eq!(uploaded.bytes, bytes);
+        assert_eq!(uploaded.alt_text, None);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn replies_to_and_deletes_tweets(pool: PgPool) {
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        let x_api = XApi::new(&x.credentials(), BotType::ALTITUDE, &pool)
             .await
             .unwrap();
+
+        let id = x_api.post("Current highest flight", "1").await.unwrap();
+        let reply = x_api.reply(&id, "Landed", "2").await.unwrap();
+        x_api.delete(&reply).await.unwrap();
+
+        assert_eq!(x.replied_to(), [None, Some(id)]);
+        assert_eq!(x.deleted(), [reply]);
     }
 }
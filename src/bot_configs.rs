@@ -0,0 +1,60 @@
+use apalis::cron::Schedule;
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+
+use crate::types::{BotConfig, BotType};
+
+/// The bot's row in the Bots table, created on first use.
+pub async fn load_bot_config(
+    executor: impl PgExecutor<'_> + Copy,
+    bot_type: &BotType,
+) -> Result<BotConfig, sqlx::Error> {
+    sqlx::query("INSERT INTO Bots (bot_type) VALUES ($1) ON CONFLICT DO NOTHING;")
+        .bind(bot_type)
+        .execute(executor)
+        .await?;
+
+    sqlx::query_as("SELECT * FROM Bots WHERE bot_type = $1;")
+        .bind(bot_type)
+        .fetch_one(executor)
+        .await
+}
+
+/// Records a run at `tick` if `schedule` is due by then.
+///
+/// Retries of the claimed tick pass again, so a failed run is still retried.
+pub async fn claim_run(
+    pool: &PgPool,
+    bot_type: &BotType,
+    schedule: &Schedule,
+    tick: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let last_run_at: Option<DateTime<Utc>> =
+        sqlx::query_scalar("SELECT last_run_at FROM Bots WHERE bot_type = $1 FOR UPDATE;")
+            .bind(bot_type)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    let due = match last_run_at {
+        None => true,
+        Some(last_run_at) if last_run_at == tick => true,
+        Some(last_run_at) => schedule
+            .after(&last_run_at)
+            .next()
+            .is_some_and(|t| t <= tick),
+    };
+
+    if due {
+        sqlx::query("UPDATE Bots SET last_run_at = $2 WHERE bot_type = $1;")
+            .bind(bot_type)
+            .bind(tick)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(due)
+}
@@ -1,21 +1,36 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use apalis::{
     cron::{CronStream, Schedule},
+    layers::retry::{RetryLayer, RetryPolicy},
     postgres::PostgresStorage,
     prelude::{Data, Monitor, WorkerBuilder, WorkerFactoryFn},
     utils::TokioExecutor,
 };
 
 use chrono::Utc;
-use shuttle_runtime::{tokio, SecretStore};
+use shuttle_runtime::{tokio, CustomError, SecretStore};
 use sqlx::PgPool;
+use warp::Filter;
 
 use crate::{
-    altitude_bot::{altitude_job, Checker as AltChecker},
-    groundspeed_bot::{groundspeed_job, Checker as GspdChecker},
+    apis::{BlueskyCredentials, MastodonCredentials, WebhookConfig, XCredentials},
+    bot_configs::load_bot_config,
+    bots::{
+        delivery_job, ranking_job, AltitudeBot, Checker, Deliverer, GroundspeedBot, JobContext,
+        RankingBot,
+    },
+    config::{BotSettings, Config},
+    routes::{auth_routes, history_routes, AuthTarget},
 };
 
+/// Attempts of a failed job before it is given up until the next tick.
+const JOB_RETRIES: usize = 3;
+
+/// Workers wake up every minute. Ranking jobs only run once their own schedule is due,
+/// queued announcements are tweeted right away.
+const TICK_SCHEDULE: &str = "0 * * * * * *";
+
 pub struct BotService {
     pub secrets: SecretStore,
     pub pool: PgPool,
@@ -23,58 +38,165 @@ pub struct BotService {
 
 #[shuttle_runtime::async_trait]
 impl shuttle_runtime::Service for BotService {
-    async fn bind(self, _addr: std::net::SocketAddr) -> Result<(), shuttle_runtime::Error> {
-        let alt_storage: PostgresStorage<AltChecker> = PostgresStorage::new(self.pool.clone());
-        let schedule = Schedule::from_str("0 0 */16 ? * * *").expect("Couldn't start scheduler.");
+    async fn bind(self, addr: std::net::SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        let schedule = Schedule::from_str(TICK_SCHEDULE).expect("Couldn't start scheduler.");
 
-        let alt_worker = WorkerBuilder::new("cron-worker")
-            .with_storage(alt_storage.clone())
-            .stream(CronStream::new(schedule.clone()).into_stream())
-            .data((alt_storage.clone(), self.secrets.clone()))
-            .build_fn(altitude_job);
+        let config = Config::from_secrets(&self.secrets).map_err(CustomError::new)?;
 
-        let alt_monitor = Monitor::<TokioExecutor>::new().register(alt_worker);
+        let alt_context = self.job_context(AltitudeBot, &config).await?;
+        let gspd_context = self.job_context(GroundspeedBot, &config).await?;
 
-        let gspd_storage: PostgresStorage<GspdChecker> = PostgresStorage::new(self.pool.clone());
-        let gspd_worker = WorkerBuilder::new("cron-worker")
-            .with_storage(gspd_storage.clone())
+        // Every bot has its own job types, so each worker only picks up its bot's jobs.
+        let alt_worker = WorkerBuilder::new(worker_name(&alt_context, "ranking"))
+            .with_storage(PostgresStorage::<Checker<AltitudeBot>>::new(
+                self.pool.clone(),
+            ))
+            .stream(CronStream::new(schedule.clone()).into_stream())
+            .layer(RetryLayer::new(RetryPolicy::retries(JOB_RETRIES)))
+            .data(alt_context.clone())
+            .build_fn(ranking_job::<AltitudeBot>);
+
+        let alt_delivery_worker = WorkerBuilder::new(worker_name(&alt_context, "delivery"))
+            .with_storage(PostgresStorage::<Deliverer<AltitudeBot>>::new(
+                self.pool.clone(),
+            ))
+            .stream(CronStream::new(schedule.clone()).into_stream())
+            .layer(RetryLayer::new(RetryPolicy::retries(JOB_RETRIES)))
+            .data(alt_context.clone())
+            .build_fn(delivery_job::<AltitudeBot>);
+
+        let gspd_worker = WorkerBuilder::new(worker_name(&gspd_context, "ranking"))
+            .with_storage(PostgresStorage::<Checker<GroundspeedBot>>::new(
+                self.pool.clone(),
+            ))
+            .stream(CronStream::new(schedule.clone()).into_stream())
+            .layer(RetryLayer::new(RetryPolicy::retries(JOB_RETRIES)))
+            .data(gspd_context.clone())
+            .build_fn(ranking_job::<GroundspeedBot>);
+
+        let gspd_delivery_worker = WorkerBuilder::new(worker_name(&gspd_context, "delivery"))
+            .with_storage(PostgresStorage::<Deliverer<GroundspeedBot>>::new(
+                self.pool.clone(),
+            ))
             .stream(CronStream::new(schedule).into_stream())
-            .data((gspd_storage.clone(), self.secrets.clone()))
-            .build_fn(groundspeed_job);
-
-        let gspd_monitor = Monitor::<TokioExecutor>::new().register(gspd_worker);
-
-        let initial_altitude_job = altitude_job(
-            AltChecker::from(Utc::now()),
-            Data::new((alt_storage.clone(), self.secrets.clone())),
-        );
-
-        let initial_groundspeed_job = groundspeed_job(
-            GspdChecker::from(Utc::now()),
-            Data::new((gspd_storage.clone(), self.secrets.clone())),
-        );
+            .layer(RetryLayer::new(RetryPolicy::retries(JOB_RETRIES)))
+            .data(gspd_context.clone())
+            .build_fn(delivery_job::<GroundspeedBot>);
+
+        let monitor = Monitor::<TokioExecutor>::new()
+            .register(alt_worker)
+            .register(alt_delivery_worker)
+            .register(gspd_worker)
+            .register(gspd_delivery_worker);
+
+        let auth_targets = HashMap::from([auth_target(&alt_context), auth_target(&gspd_context)]);
+        let bots = auth_targets
+            .iter()
+            .map(|(name, target)| (name.clone(), target.bot_type.clone()))
+            .collect();
+        let routes = auth_routes(
+            self.pool.clone(),
+            auth_targets,
+            config.onboarding_token.clone(),
+        )
+        .or(history_routes(self.pool.clone(), bots))
+        .unify();
+        let server = warp::serve(routes).run(addr);
+
+        let initial_altitude_job = ranking_job(Checker::from(Utc::now()), Data::new(alt_context));
+
+        let initial_groundspeed_job =
+            ranking_job(Checker::from(Utc::now()), Data::new(gspd_context));
 
         // Run initial jobs concurrently
         let (alt_result, gspd_result) = tokio::join!(initial_altitude_job, initial_groundspeed_job);
 
         if let Err(e) = alt_result {
-            eprintln!("Initial altitude job failed: {:?}", e);
+            eprintln!("Initial altitude job failed: {}", e);
         }
 
         if let Err(e) = gspd_result {
-            eprintln!("Initial groundspeed job failed: {:?}", e);
+            eprintln!("Initial groundspeed job failed: {}", e);
         }
 
-        // Run monitors concurrently
+        // Run the monitor and the HTTP server concurrently
         tokio::select! {
-            _ = alt_monitor.run() => {
-                eprintln!("Altitude monitor stopped.");
+            _ = monitor.run() => {
+                eprintln!("Monitor stopped.");
             },
-            _ = gspd_monitor.run() => {
-                eprintln!("Groundspeed monitor stopped.");
+            _ = server => {
+                eprintln!("HTTP server stopped.");
             },
         }
 
         Ok(())
     }
 }
+
+impl BotService {
+    /// Reads a bot's secrets and validates its overrides in the Bots table.
+    async fn job_context<B: RankingBot>(
+        &self,
+        bot: B,
+        config: &Config,
+    ) -> Result<JobContext<B>, shuttle_runtime::Error> {
+        let credentials = XCredentials::from_secrets(
+            &self.secrets,
+            bot.credentials_key(),
+            config.x_endpoints.clone(),
+        )
+        .map_err(CustomError::new)?;
+        let mastodon = MastodonCredentials::from_secrets(
+            &self.secrets,
+            bot.credentials_key(),
+            &config.x_endpoints.redirect_url,
+        )
+        .map_err(CustomError::new)?;
+        let bluesky = BlueskyCredentials::from_secrets(&self.secrets, bot.credentials_key())
+            .map_err(CustomError::new)?;
+        let webhooks = WebhookConfig::from_secrets(&self.secrets, bot.credentials_key())
+            .map_err(CustomError::new)?;
+        let settings = BotSettings::from_secrets(
+            &self.secrets,
+            bot.credentials_key(),
+            bot.default_threshold(),
+            bot.default_template(),
+        )
+        .map_err(CustomError::new)?;
+
+        let overrides = load_bot_config(&self.pool, &bot.bot_type())
+            .await
+            .map_err(CustomError::new)?;
+        settings
+            .with_overrides(&overrides)
+            .map_err(CustomError::new)?;
+
+        Ok(JobContext {
+            bot,
+            pool: self.pool.clone(),
+            config: config.clone(),
+            settings,
+            credentials,
+            mastodon,
+            bluesky,
+            webhooks,
+        })
+    }
+}
+
+/// e.g. `altitude-ranking-worker`.
+fn worker_name<B: RankingBot>(context: &JobContext<B>, role: &str) -> String {
+    format!("{}-{}-worker", context.bot.name(), role)
+}
+
+fn auth_target<B: RankingBot>(context: &JobContext<B>) -> (String, AuthTarget) {
+    (
+        context.bot.name().to_string(),
+        AuthTarget {
+            bot_type: context.bot.bot_type(),
+            credentials: context.credentials.clone(),
+            mastodon: context.mastodon.clone(),
+            bluesky: context.bluesky.clone(),
+        },
+    )
+}
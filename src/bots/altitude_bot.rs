@@ -0,0 +1,48 @@
+use crate::types::{BotType, Flight};
+
+use super::RankingBot;
+
+#[derive(Clone, Debug)]
+pub struct AltitudeBot;
+
+impl RankingBot for AltitudeBot {
+    const RANKING_JOB: &'static str = "altitude::RankingJob";
+    const DELIVERY_JOB: &'static str = "altitude::DeliveryJob";
+
+    fn name(&self) -> &'static str {
+        "altitude"
+    }
+
+    fn bot_type(&self) -> BotType {
+        BotType::ALTITUDE
+    }
+
+    fn credentials_key(&self) -> &'static str {
+        "ALT"
+    }
+
+    fn default_threshold(&self) -> u32 {
+        450
+    }
+
+    fn search_query(&self, threshold: u32) -> String {
+        format!("-aboveAltitude {}", threshold)
+    }
+
+    fn ident_filter(&self) -> &[&'static str] {
+        &["HBAL"]
+    }
+
+    fn metric(&self, flight: &Flight) -> Option<i32> {
+        flight.altitude
+    }
+
+    fn default_template(&self) -> &'static str {
+        "Current highest flight: {{ident}}\n\
+        Altitude: {{altitude}}\n\
+        Groundspeed: {{speed}}\n\
+        Origin: {{origin}}\n\
+        Destination: {{destination}}\n\n\
+        More info:\n{{link}}"
+    }
+}
@@ -0,0 +1,694 @@
+use std::marker::PhantomData;
+
+use apalis::prelude::{Data, Job};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+
+use crate::{
+    apis::{BlueskyApi, MastodonApi, Publisher, WebhookPublisher, XApi},
+    error::{BotError, PublishError},
+    posts::{
+        mark_delivered, next_pending_post, pending_providers, record_failed_delivery, thread_parent,
+    },
+    types::{AuthProvider, BotType, Post},
+};
+
+use super::{JobContext, RankingBot};
+
+/// Attempts to deliver an announcement to a network before it is marked as failed.
+const MAX_DELIVERY_ATTEMPTS: i32 = 5;
+
+/// Wait after the first failed attempt, doubled after every further one.
+const RETRY_BACKOFF: Duration = Duration::minutes(2);
+
+/// Tick of the worker posting the announcements queued by the ranking jobs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deliverer<B> {
+    pub time: DateTime<Utc>,
+    #[serde(skip)]
+    bot: PhantomData<fn() -> B>,
+}
+
+impl<B> Default for Deliverer<B> {
+    fn default() -> Self {
+        Self::from(DateTime::default())
+    }
+}
+
+impl<B> From<DateTime<Utc>> for Deliverer<B> {
+    fn from(time: DateTime<Utc>) -> Self {
+        Self {
+            time,
+            bot: PhantomData,
+        }
+    }
+}
+
+impl<B: RankingBot> Job for Deliverer<B> {
+    const NAME: &'static str = B::DELIVERY_JOB;
+}
+
+pub async fn delivery_job<B: RankingBot>(
+    _job: Deliverer<B>,
+    data: Data<JobContext<B>>,
+) -> Result<(), BotError> {
+    let result = deliver_pending(&data).await;
+
+    if let Err(e) = &result {
+        eprintln!("[{:?}] Delivery job failed: {}", data.bot.bot_type(), e);
+    }
+
+    result
+}
+
+/// Delivers the due announcements of a bot to each network, oldest first.
+///
+/// A failing network doesn't hold up the others; the first error is returned once all were tried.
+/// Announcements the network rejected, or that couldn't be posted because the session couldn't
+/// be refreshed, aren't errors of the job: they back off in the outbox, so retrying the job
+/// wouldn't deliver them any sooner.
+async fn deliver_pending<B: RankingBot>(context: &JobContext<B>) -> Result<(), BotError> {
+    let bot = &context.bot;
+
+    // Most ticks have nothing to deliver, so don't touch the sessions for them.
+    let providers = pending_providers(&context.pool, &bot.bot_type()).await?;
+    let mut result = Ok(());
+
+    for provider in providers {
+        let publisher = match open_publisher(context, &provider).await {
+            Err(BotError::NotAuthorized(bot_type, provider)) => {
+                println!(
+                    "[{:?}] Not authorized with {:?} yet, keeping announcements queued.",
+                    bot_type, provider
+                );
+                continue;
+            }
+            Err(e @ BotError::Auth(..)) => {
+                result = result.and(back_off_pending(context, &provider, &e).await);
+                continue;
+            }
+            publisher => publisher,
+        };
+
+        let delivered = match publisher {
+            Ok(publisher) => deliver_to(context, publisher.as_ref()).await,
+            Err(e) => Err(e),
+        };
+
+        result = result.and(delivered);
+    }
+
+    result
+}
+
+async fn open_publisher<B: RankingBot>(
+    context: &JobContext<B>,
+    provider: &AuthProvider,
+) -> Result<Box<dyn Publisher>, BotError> {
+    let bot_type = context.bot.bot_type();
+
+    Ok(match provider {
+        AuthProvider::X => {
+            Box::new(XApi::new(&context.credentials, bot_type, &context.pool).await?)
+        }
+        AuthProvider::MASTODON => {
+            // Announcements queued before a network was unconfigured wait for it to return.
+            let Some(credentials) = &context.mastodon else {
+                return Err(BotError::NotAuthorized(bot_type, AuthProvider::MASTODON));
+            };
+            Box::new(MastodonApi::new(credentials, bot_type, &context.pool).await?)
+        }
+        AuthProvider::BLUESKY => {
+            let Some(credentials) = &context.bluesky else {
+                return Err(BotError::NotAuthorized(bot_type, AuthProvider::BLUESKY));
+            };
+            Box::new(BlueskyApi::new(credentials, bot_type, &context.pool).await?)
+        }
+        AuthProvider::WEBHOOK => {
+            let Some(config) = &context.webhooks else {
+                return Err(BotError::NotAuthorized(bot_type, AuthProvider::WEBHOOK));
+            };
+            Box::new(WebhookPublisher::new(config, bot_type))
+        }
+    })
+}
+
+async fn deliver_to<B: RankingBot>(
+    context: &JobContext<B>,
+    publisher: &dyn Publisher,
+) -> Result<(), BotError> {
+    let bot_type = context.bot.bot_type();
+    let provider = publisher.provider();
+
+    loop {
+        let mut tx = context.pool.begin().await?;
+
+        // The row stays locked while it is posted, so concurrent deliveries never post it twice.
+        let Some(post) = next_pending_post(&mut *tx, &bot_type, &provider).await? else {
+            return Ok(());
+        };
+
+        let parent = thread_parent(&mut *tx, &post).await?;
+
+        match publish(publisher, &post, parent.as_deref()).await {
+            Ok(external_id) => {
+                let recorded = match mark_delivered(&mut *tx, post.id, Some(&external_id)).await {
+                    Ok(()) => tx.commit().await,
+                    Err(e) => Err(e),
+                };
+
+                // The row is still pending, so take the post down rather than post it twice.
+                if let Err(e) = recorded {
+                    if let Err(delete_error) = publisher.delete(&external_id).await {
+                        eprintln!(
+                            "[{:?}] Couldn't take down {} on {:?} after failing to record it: {}",
+                            bot_type, external_id, provider, delete_error
+                        );
+                    }
+                    return Err(e.into());
+                }
+
+                println!(
+                    "[{:?}] Posted {} to {:?} ({}).",
+                    bot_type, post.ident, provider, external_id
+                );
+            }
+            // An earlier attempt went through but couldn't be marked as delivered.
+            Err(BotError::Publish(_, PublishError::Duplicate)) => {
+                mark_delivered(&mut *tx, post.id, None).await?;
+                tx.commit().await?;
+                println!(
+                    "[{:?}] {} was already posted to {:?}.",
+                    bot_type, post.ident, provider
+                );
+            }
+            Err(e) => fail_delivery(tx, &bot_type, &provider, &post, &e).await?,
+        }
+    }
+}
+
+/// Counts an attempt against every due announcement of a network that couldn't be opened,
+/// so they back off and eventually fail like announcements the network rejected.
+async fn back_off_pending<B: RankingBot>(
+    context: &JobContext<B>,
+    provider: &AuthProvider,
+    error: &BotError,
+) -> Result<(), BotError> {
+    let bot_type = context.bot.bot_type();
+
+    loop {
+        let mut tx = context.pool.begin().await?;
+
+        let Some(post) = next_pending_post(&mut *tx, &bot_type, provider).await? else {
+            return Ok(());
+        };
+
+        fail_delivery(tx, &bot_type, provider, &post, error).await?;
+    }
+}
+
+/// Records a failed attempt to deliver `post`, backing it off or giving up on it.
+async fn fail_delivery(
+    mut tx: Transaction<'_, Postgres>,
+    bot_type: &BotType,
+    provider: &AuthProvider,
+    post: &Post,
+    error: &BotError,
+) -> Result<(), BotError> {
+    let attempts = post.attempts + 1;
+    let next_attempt_at = next_attempt_at(error, attempts, Utc::now());
+    record_failed_delivery(
+        &mut *tx,
+        post.id,
+        &error.to_string(),
+        MAX_DELIVERY_ATTEMPTS,
+        next_attempt_at,
+    )
+    .await?;
+    tx.commit().await?;
+
+    if attempts >= MAX_DELIVERY_ATTEMPTS {
+        eprintln!(
+            "[{:?}] Gave up posting {} to {:?} after {} attempts: {}",
+            bot_type, post.ident, provider, attempts, error
+        );
+    } else {
+        eprintln!(
+            "[{:?}] Couldn't post {} to {:?}, trying again at {}: {}",
+            bot_type, post.ident, provider, next_attempt_at, error
+        );
+    }
+
+    Ok(())
+}
+
+/// Announces `post`, in the thread of `parent` if the flight was announced on the network
+/// before and the network has threads.
+async fn publish(
+    publisher: &dyn Publisher,
+    post: &Post,
+    parent: Option<&str>,
+) -> Result<String, BotError> {
+    let Some(parent) = parent else {
+        return publisher.announce(post).await;
+    };
+
+    match publisher
+        .reply(parent, &post.text, &post.id.to_string())
+        .await
+    {
+        Err(BotError::Publish(_, PublishError::Unsupported(_))) => publisher.announce(post).await,
+        result => result,
+    }
+}
+
+/// When to try an announcement again after its `attempts`th delivery failed with `error`:
+/// once a rate limit resets, otherwise after an exponential backoff.
+fn next_attempt_at(error: &BotError, attempts: i32, now: DateTime<Utc>) -> DateTime<Utc> {
+    match error {
+        BotError::Publish(_, PublishError::RateLimited { reset: Some(reset) }) if *reset > now => {
+            *reset
+        }
+        _ => now + RETRY_BACKOFF * 2_i32.pow(attempts.clamp(1, MAX_DELIVERY_ATTEMPTS) as u32 - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+    use warp::http::StatusCode;
+
+    use super::*;
+    use crate::{
+        apis::{WebhookFormat, WebhookTarget},
+        bots::AltitudeBot,
+        posts::{recent_posts, record_post, NewPost},
+        test_support::{
+            context, context_with, insert_mastodon_session, insert_session, valid_expiry,
+            MockAeroApi, MockMastodon, MockWebhook, MockX,
+        },
+        types::{BotType, Flight, PostStatus},
+    };
+
+    async fn queue_post(pool: &PgPool, ident: &str) {
+        let flight = Flight {
+            ident: ident.to_string(),
+            fa_flight_id: None,
+            ranking: BotType::ALTITUDE,
+            altitude: Some(510),
+            groundspeed: None,
+            origin: None,
+            aircraft_type: None,
+            destination: None,
+            position_time: None,
+        };
+
+        record_post(
+            pool,
+            NewPost {
+                bot_type: BotType::ALTITUDE,
+                flight: &flight,
+                text: &format!("Current highest flight: {}", ident),
+                status: PostStatus::PENDING,
+                provider: Some(AuthProvider::X),
+                target: None,
+                announcement_id: None,
+                track: None,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    /// Ends the backoff of the announcements that failed to deliver.
+    async fn make_due(pool: &PgPool) {
+        sqlx::query("UPDATE Posts SET next_attempt_at = NOW();")
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn deliver(pool: &PgPool, x: &MockX) -> Result<(), BotError> {
+        let aero_api = MockAeroApi::start("{}", &[]);
+        delivery_job(
+            Deliverer::default(),
+            context(AltitudeBot, pool, &aero_api, x),
+        )
+        .await
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn delivers_pending_posts_in_order(pool: PgPool) {
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        queue_post(&pool, "N650GD").await;
+        queue_post(&pool, "UAE215").await;
+
+        deliver(&pool, &x).await.unwrap();
+        deliver(&pool, &x).await.unwrap();
+
+        assert_eq!(
+            x.tweets(),
+            [
+                "Current highest flight: N650GD",
+                "Current highest flight: UAE215"
+            ]
+        );
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert!(posts.iter().all(|p| p.status == PostStatus::POSTED));
+        assert!(posts.iter().all(|p| p.delivered_at.is_some()));
+        assert_eq!(posts[1].external_id.as_deref(), Some("1"));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn does_not_touch_session_without_pending_posts(pool: PgPool) {
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, Utc::now()).await;
+
+        deliver(&pool, &x).await.unwrap();
+
+        assert_eq!(x.refreshes(), 0);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn keeps_posts_queued_until_authorized(pool: PgPool) {
+        let x = MockX::start();
+        queue_post(&pool, "N650GD").await;
+
+        deliver(&pool, &x).await.unwrap();
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts[0].status, PostStatus::PENDING);
+        assert_eq!(posts[0].attempts, 0);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn refreshes_rejected_token_and_retries(pool: PgPool) {
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        queue_post(&pool, "N650GD").await;
+        x.fail_next_tweet(StatusCode::UNAUTHORIZED, "Unauthorized");
+
+        deliver(&pool, &x).await.unwrap();
+
+        assert_eq!(x.refreshes(), 1);
+        assert_eq!(x.tweets().len(), 1);
+
+        let refresh_token: String =
+            sqlx::query_scalar("SELECT refresh_token FROM Sessions WHERE bot_type = $1;")
+                .bind(BotType::ALTITUDE)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(refresh_token, "refresh-token-1");
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn backs_off_posts_when_session_cannot_be_refreshed(pool: PgPool) {
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, Utc::now()).await;
+        sqlx::query("UPDATE Sessions SET refresh_token = NULL;")
+            .execute(&pool)
+            .await
+            .unwrap();
+        queue_post(&pool, "N650GD").await;
+        queue_post(&pool, "UAE215").await;
+
+        // The job itself succeeds, retrying it wouldn't refresh the session either.
+        deliver(&pool, &x).await.unwrap();
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert!(x.tweets().is_empty());
+        assert!(posts.iter().all(|p| p.status == PostStatus::PENDING
+            && p.attempts == 1
+            && p.next_attempt_at.unwrap() > Utc::now()
+            && p.error
+                .as_deref()
+                .unwrap()
+                .contains("no refresh token stored")));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn failed_delivery_is_retried_until_given_up(pool: PgPool) {
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        queue_post(&pool, "N650GD").await;
+
+        x.fail_next_tweet(StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable");
+        deliver(&pool, &x).await.unwrap();
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts[0].status, PostStatus::PENDING);
+        assert_eq!(posts[0].attempts, 1);
+        assert!(posts[0].error.as_deref().unwrap().contains("503"));
+        assert!(posts[0].next_attempt_at.unwrap() > Utc::now() + Duration::minutes(1));
+
+        // The next tick leaves it alone while it backs off.
+        deliver(&pool, &x).await.unwrap();
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts[0].attempts, 1);
+
+        for _ in 1..MAX_DELIVERY_ATTEMPTS {
+            make_due(&pool).await;
+            x.fail_next_tweet(StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable");
+            deliver(&pool, &x).await.unwrap();
+        }
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts[0].status, PostStatus::FAILED);
+        assert_eq!(posts[0].attempts, MAX_DELIVERY_ATTEMPTS);
+
+        make_due(&pool).await;
+        deliver(&pool, &x).await.unwrap();
+        assert!(x.tweets().is_empty());
+    }
+
+    #[test]
+    fn backs_off_until_rate_limit_resets() {
+        let now = Utc::now();
+        let rate_limited =
+            |reset| BotError::Publish(AuthProvider::X, PublishError::RateLimited { reset });
+        let unauthorized = BotError::Publish(AuthProvider::X, PublishError::Unauthorized);
+
+        let reset = now + Duration::minutes(15);
+        assert_eq!(next_attempt_at(&rate_limited(Some(reset)), 1, now), reset);
+        assert_eq!(
+            next_attempt_at(&rate_limited(None), 1, now),
+            now + RETRY_BACKOFF
+        );
+        assert_eq!(next_attempt_at(&unauthorized, 1, now), now + RETRY_BACKOFF);
+        assert_eq!(
+            next_attempt_at(&unauthorized, 3, now),
+            now + RETRY_BACKOFF * 4
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn duplicate_counts_as_delivered(pool: PgPool) {
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        queue_post(&pool, "N650GD").await;
+        x.fail_next_tweet(
+            StatusCode::FORBIDDEN,
+            "You are not allowed to create a Tweet with duplicate content.",
+        );
+
+        deliver(&pool, &x).await.unwrap();
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts[0].status, PostStatus::POSTED);
+        assert_eq!(posts[0].external_id, None);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn failing_network_does_not_hold_up_the_others(pool: PgPool) {
+        let aero_api = MockAeroApi::start("{}", &[]);
+        let x = MockX::start();
+        let mastodon = MockMastodon::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        insert_mastodon_session(&pool, BotType::ALTITUDE).await;
+        queue_post(&pool, "N650GD").await;
+        sqlx::query(
+            "INSERT INTO Posts (bot_type, ident, text, status, provider, announcement_id) \
+            SELECT bot_type, ident, text, status, 'MASTODON', announcement_id FROM Posts;",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        x.fail_next_tweet(StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable");
+
+        let context = context_with(AltitudeBot, &pool, &aero_api, &x, |c| {
+            c.mastodon = Some(mastodon.credentials())
+        });
+        delivery_job(Deliverer::default(), context).await.unwrap();
+
+        assert_eq!(mastodon.statuses(), ["Current highest flight: N650GD"]);
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        let status = |provider: AuthProvider| {
+            posts
+                .iter()
+                .find(|p| p.provider.as_ref() == Some(&provider))
+                .map(|p| p.status.clone())
+        };
+        assert_eq!(status(AuthProvider::X), Some(PostStatus::PENDING));
+        assert_eq!(status(AuthProvider::MASTODON), Some(PostStatus::POSTED));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn retries_only_the_failing_webhook(pool: PgPool) {
+        let aero_api = MockAeroApi::start("{}", &[]);
+        let x = MockX::start();
+        let webhook = MockWebhook::start();
+        let webhooks = webhook.config(vec![
+            webhook.target("json", WebhookFormat::Json),
+            webhook.target("slack", WebhookFormat::Slack),
+        ]);
+        queue_post(&pool, "N650GD").await;
+        sqlx::query("UPDATE Posts SET provider = 'WEBHOOK', target = $1;")
+            .bind(webhooks.targets[0].key())
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO Posts (bot_type, ident, text, flight, status, provider, target, announcement_id) \
+            SELECT bot_type, ident, text, flight, status, provider, $1, announcement_id FROM Posts;",
+        )
+        .bind(webhooks.targets[1].key())
+        .execute(&pool)
+        .await
+        .unwrap();
+        webhook.reject("slack", StatusCode::BAD_REQUEST);
+
+        for _ in 0..3 {
+            let context = context_with(AltitudeBot, &pool, &aero_api, &x, |c| {
+                c.webhooks = Some(webhooks.clone())
+            });
+            delivery_job(Deliverer::default(), context).await.unwrap();
+            make_due(&pool).await;
+        }
+
+        // The healthy webhook got the announcement once, the failing one was tried every tick.
+        assert_eq!(webhook.requests().len(), 1);
+        assert_eq!(webhook.requests()[0].path, "/json");
+        assert_eq!(webhook.attempts(), 4);
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        let row = |target: &WebhookTarget| {
+            posts
+                .iter()
+                .find(|p| p.target == Some(target.key()))
+                .unwrap()
+        };
+        let (json, slack) = (row(&webhooks.targets[0]), row(&webhooks.targets[1]));
+        assert_eq!(json.status, PostStatus::POSTED);
+        assert_eq!(json.attempts, 1);
+        assert_eq!(slack.status, PostStatus::PENDING);
+        assert_eq!(slack.attempts, 3);
+        // The outbox names the webhook without giving away its URL.
+        assert!(!slack.error.as_deref().unwrap().contains(&webhook.url));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn threads_announcements_of_the_same_flight(pool: PgPool) {
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        queue_post(&pool, "N650GD").await;
+        deliver(&pool, &x).await.unwrap();
+
+        queue_post(&pool, "UAE215").await;
+        queue_post(&pool, "N650GD").await;
+        deliver(&pool, &x).await.unwrap();
+
+        assert_eq!(x.replied_to(), [None, None, Some("1".to_string())]);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn announces_again_where_replies_are_unsupported(pool: PgPool) {
+        let aero_api = MockAeroApi::start("{}", &[]);
+        let x = MockX::start();
+        let webhook = MockWebhook::start();
+        let webhooks = webhook.config(vec![webhook.target("json", WebhookFormat::Json)]);
+        let context = context_with(AltitudeBot, &pool, &aero_api, &x, |c| {
+            c.webhooks = Some(webhooks.clone())
+        });
+
+        for _ in 0..2 {
+            queue_post(&pool, "N650GD").await;
+            sqlx::query("UPDATE Posts SET provider = 'WEBHOOK', target = $1;")
+                .bind(webhooks.targets[0].key())
+                .execute(&pool)
+                .await
+                .unwrap();
+            delivery_job(Deliverer::default(), context.clone())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(webhook.requests().len(), 2);
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert!(posts.iter().all(|p| p.status == PostStatus::POSTED));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn takes_down_post_that_could_not_be_recorded(pool: PgPool) {
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        queue_post(&pool, "N650GD").await;
+        sqlx::query(
+            "CREATE FUNCTION reject_delivery() RETURNS trigger AS $$ \
+            BEGIN RAISE EXCEPTION 'disk full'; END; $$ LANGUAGE plpgsql;",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TRIGGER reject_delivery BEFORE UPDATE ON Posts FOR EACH ROW \
+            WHEN (NEW.status = 'POSTED') EXECUTE FUNCTION reject_delivery();",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            deliver(&pool, &x).await,
+            Err(BotError::Database(_))
+        ));
+
+        assert_eq!(x.tweets().len(), 1);
+        assert_eq!(x.deleted(), ["1"]);
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts[0].status, PostStatus::PENDING);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn keeps_posts_queued_for_unconfigured_network(pool: PgPool) {
+        let x = MockX::start();
+        queue_post(&pool, "N650GD").await;
+        sqlx::query("UPDATE Posts SET provider = 'MASTODON';")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        deliver(&pool, &x).await.unwrap();
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts[0].status, PostStatus::PENDING);
+        assert_eq!(posts[0].attempts, 0);
+    }
+}
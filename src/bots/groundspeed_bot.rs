@@ -0,0 +1,44 @@
+use crate::types::{BotType, Flight};
+
+use super::RankingBot;
+
+#[derive(Clone, Debug)]
+pub struct GroundspeedBot;
+
+impl RankingBot for GroundspeedBot {
+    const RANKING_JOB: &'static str = "groundspeed::RankingJob";
+    const DELIVERY_JOB: &'static str = "groundspeed::DeliveryJob";
+
+    fn name(&self) -> &'static str {
+        "groundspeed"
+    }
+
+    fn bot_type(&self) -> BotType {
+        BotType::GROUNDSPEED
+    }
+
+    fn credentials_key(&self) -> &'static str {
+        "GSPD"
+    }
+
+    fn default_threshold(&self) -> u32 {
+        650
+    }
+
+    fn search_query(&self, threshold: u32) -> String {
+        format!("-aboveGroundspeed {}", threshold)
+    }
+
+    fn metric(&self, flight: &Flight) -> Option<i32> {
+        flight.groundspeed
+    }
+
+    fn default_template(&self) -> &'static str {
+        "Current fastest flight: {{ident}}\n\
+        Groundspeed: {{speed}}\n\
+        Altitude: {{altitude}}\n\
+        Origin: {{origin}}\n\
+        Destination: {{destination}}\n\n\
+        More info:\n{{link}}"
+    }
+}
@@ -0,0 +1,9 @@
+mod altitude_bot;
+mod delivery;
+mod groundspeed_bot;
+mod ranking_bot;
+
+pub use altitude_bot::AltitudeBot;
+pub use delivery::{delivery_job, Deliverer};
+pub use groundspeed_bot::GroundspeedBot;
+pub use ranking_bot::{ranking_job, Checker, JobContext, RankingBot};
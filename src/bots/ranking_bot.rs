@@ -0,0 +1,1185 @@
+use std::marker::PhantomData;
+
+use apalis::prelude::{Data, Job};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::{
+    apis::{
+        AeroApi, BlueskyApi, BlueskyCredentials, MastodonApi, MastodonCredentials, WebhookConfig,
+        WebhookTarget, XApi, XCredentials,
+    },
+    bot_configs::{claim_run, load_bot_config},
+    config::{BotSettings, Config},
+    error::BotError,
+    observations::{prune_observations, record_observations},
+    posts::{next_announcement_id, record_post, was_announced_since, NewPost},
+    ranking::{top_flights, RANKING_SIZE},
+    types::{AuthProvider, BotType, Flight, Observation, PostStatus, TrackPoint},
+    usage::{dollars, reserve_pages, settle},
+};
+
+/// A bot that announces whenever a new aircraft leads a ranking of AeroAPI search results.
+pub trait RankingBot: Clone + Send + Sync + 'static {
+    /// Lowercase name used in URLs, e.g. `/auth/{name}/start`.
+    fn name(&self) -> &'static str;
+
+    /// apalis job type of the bot's ranking ticks, unique per bot.
+    const RANKING_JOB: &'static str;
+
+    /// apalis job type of the bot's delivery ticks, unique per bot.
+    const DELIVERY_JOB: &'static str;
+
+    /// Ranking the bot maintains in the Flights table.
+    fn bot_type(&self) -> BotType;
+
+    /// Infix of the bot's `X_{key}_CLIENT_ID` and `X_{key}_CLIENT_SECRET` secrets.
+    fn credentials_key(&self) -> &'static str;
+
+    /// Lower bound of the metric for a flight to be returned by AeroAPI, unless configured.
+    fn default_threshold(&self) -> u32;
+
+    /// AeroAPI `/flights/search` query selecting the flights above `threshold`.
+    fn search_query(&self, threshold: u32) -> String;
+
+    /// Ident prefixes that are never ranked (e.g. balloons).
+    fn ident_filter(&self) -> &[&'static str] {
+        &[]
+    }
+
+    /// Value the flights are ranked by.
+    fn metric(&self, flight: &Flight) -> Option<i32>;
+
+    /// Announcement template unless configured, see [`crate::templates::TweetTemplate`].
+    fn default_template(&self) -> &'static str;
+}
+
+/// A leader tweeted within this window isn't tweeted again when it regains the lead.
+const REPOST_COOLDOWN: Duration = Duration::hours(24);
+
+/// Tick of a bot's ranking worker, stored under the bot's own job type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checker<B> {
+    pub time: DateTime<Utc>,
+    #[serde(skip)]
+    bot: PhantomData<fn() -> B>,
+}
+
+impl<B> Default for Checker<B> {
+    fn default() -> Self {
+        Self::from(DateTime::default())
+    }
+}
+
+impl<B> From<DateTime<Utc>> for Checker<B> {
+    fn from(time: DateTime<Utc>) -> Self {
+        Self {
+            time,
+            bot: PhantomData,
+        }
+    }
+}
+
+impl<B: RankingBot> Job for Checker<B> {
+    const NAME: &'static str = B::RANKING_JOB;
+}
+
+#[derive(Clone)]
+pub struct JobContext<B: RankingBot> {
+    pub bot: B,
+    pub pool: PgPool,
+    pub config: Config,
+    pub settings: BotSettings,
+    pub credentials: XCredentials,
+    /// Set if the bot also announces on Mastodon.
+    pub mastodon: Option<MastodonCredentials>,
+    /// Set if the bot also announces on Bluesky.
+    pub bluesky: Option<BlueskyCredentials>,
+    /// Set if the bot also announces on chat webhooks.
+    pub webhooks: Option<WebhookConfig>,
+}
+
+impl<B: RankingBot> JobContext<B> {
+    /// Networks the bot has been onboarded with, i.e. the ones announcements are queued for.
+    /// Webhooks need no onboarding.
+    async fn authorized_providers(&self) -> Result<Vec<AuthProvider>, BotError> {
+        let bot_type = self.bot.bot_type();
+        let mut providers = vec![];
+
+        if XApi::is_authorized(&bot_type, &self.pool).await? {
+            providers.push(AuthProvider::X);
+        }
+
+        if self.mastodon.is_some() && MastodonApi::is_authorized(&bot_type, &self.pool).await? {
+            providers.push(AuthProvider::MASTODON);
+        }
+
+        if self.bluesky.is_some() && BlueskyApi::is_authorized(&bot_type, &self.pool).await? {
+            providers.push(AuthProvider::BLUESKY);
+        }
+
+        if self.webhooks.is_some() {
+            providers.push(AuthProvider::WEBHOOK);
+        }
+
+        Ok(providers)
+    }
+}
+
+pub async fn ranking_job<B: RankingBot>(
+    job: Checker<B>,
+    data: Data<JobContext<B>>,
+) -> Result<(), BotError> {
+    let result = run(&data, job.time).await;
+
+    if let Err(e) = &result {
+        eprintln!("[{:?}] Ranking job failed: {}", data.bot.bot_type(), e);
+    }
+
+    result
+}
+
+async fn run<B: RankingBot>(context: &JobContext<B>, tick: DateTime<Utc>) -> Result<(), BotError> {
+    let bot = &context.bot;
+    let pool = &context.pool;
+    let config = &context.config;
+
+    // The Bots table is read on every tick, so changes apply without a redeploy.
+    let overrides = load_bot_config(pool, &bot.bot_type()).await?;
+    let settings = context.settings.with_overrides(&overrides)?;
+
+    if !settings.enabled || !claim_run(pool, &bot.bot_type(), &settings.schedule, tick).await? {
+        return Ok(());
+    }
+
+    // Dry runs never post, so they work before the bot is onboarded.
+    let providers = context.authorized_providers().await?;
+    if !settings.dry_run && providers.is_empty() {
+        println!(
+            "[{:?}] Not authorized with any network yet, skipping. Onboard via /auth/{}/start.",
+            bot.bot_type(),
+            bot.name()
+        );
+        return Ok(());
+    }
+
+    let fetched_at = Utc::now();
+    let reservation = reserve_pages(
+        pool,
+        config.aero_api_monthly_budget,
+        config.aero_api_result_set_cost,
+        config.aero_api_max_pages,
+        fetched_at,
+    )
+    .await?;
+
+    if let Some(remaining) = reservation.remaining {
+        println!(
+            "[{:?}] AeroAPI budget left this month: {}.",
+            bot.bot_type(),
+            dollars(remaining)
+        );
+    }
+
+    if reservation.pages == 0 {
+        println!(
+            "[{:?}] AeroAPI budget exhausted, skipping search.",
+            bot.bot_type()
+        );
+        return Ok(());
+    }
+
+    if reservation.pages < config.aero_api_max_pages {
+        println!(
+            "[{:?}] AeroAPI budget only covers {} page(s).",
+            bot.bot_type(),
+            reservation.pages
+        );
+    }
+
+    let aero_api = AeroApi::new(
+        config.aero_api_url.clone(),
+        config.aero_api_key.clone(),
+        config.aero_api_result_set_cost,
+    );
+
+    let results = aero_api
+        .search_flights(&bot.search_query(settings.threshold), reservation.pages)
+        .await;
+
+    // Pages fetched before a failure are billed all the same.
+    settle(pool, &reservation, aero_api.pages_fetched()).await?;
+    let results = results?;
+
+    println!(
+        "[{:?}] Fetched {} AeroAPI page(s) for {}.",
+        bot.bot_type(),
+        aero_api.pages_fetched(),
+        dollars(aero_api.spent())
+    );
+
+    let observations: Vec<Observation> = results
+        .iter()
+        .filter_map(|f| f.observation(bot.bot_type(), fetched_at))
+        .collect();
+
+    // The search is already paid for, so a failure to archive it shouldn't fail the run.
+    if let Err(e) = archive_observations(context, &observations).await {
+        eprintln!("[{:?}] Couldn't store observations: {}", bot.bot_type(), e);
+    }
+
+    let flights: Vec<Flight> = results
+        .into_iter()
+        .map(|f| f.into_flight(bot.bot_type()))
+        .filter(|f| !bot.ident_filter().iter().any(|&p| f.ident.starts_with(p)))
+        .collect();
+
+    let flights = top_flights(flights, RANKING_SIZE, |f| bot.metric(f));
+
+    let Some(flight) = flights.first() else {
+        return Ok(());
+    };
+
+    let db_flights: Vec<Flight> =
+        sqlx::query_as("SELECT * FROM Flights WHERE Flights.ranking = $1;")
+            .bind(bot.bot_type())
+            .fetch_all(pool)
+            .await?;
+
+    let db_leader = top_flights(db_flights, 1, |f| bot.metric(f));
+
+    let text = settings.template.render(flight);
+    let post = |status, provider, target, announcement_id| NewPost {
+        bot_type: bot.bot_type(),
+        flight,
+        text: &text,
+        status,
+        provider,
+        target,
+        announcement_id,
+        track: None,
+    };
+
+    if db_leader.first().is_some_and(|f| f.is_same_flight(flight)) {
+        record_post(pool, post(PostStatus::SKIPPED, None, None, None)).await?;
+        return Ok(());
+    }
+
+    if settings.dry_run {
+        println!("[{:?}] Dry run, not tweeting:\n{}", bot.bot_type(), text);
+        record_post(pool, post(PostStatus::DRY_RUN, None, None, None)).await?;
+        return Ok(());
+    }
+
+    // A flight that briefly lost the lead and took it back isn't news.
+    let recently_announced =
+        was_announced_since(pool, &bot.bot_type(), flight, Utc::now() - REPOST_COOLDOWN).await?;
+
+    // Only X attaches a map, and the track is fetched before the transaction so the ranking
+    // isn't kept locked while AeroAPI answers.
+    let track = if !recently_announced && providers.contains(&AuthProvider::X) {
+        fetch_track(context, &aero_api, flight).await
+    } else {
+        None
+    };
+
+    let mut tx = pool.begin().await?;
+    replace_ranking(&mut tx, &bot.bot_type(), &flights).await?;
+
+    if recently_announced {
+        record_post(&mut *tx, post(PostStatus::SKIPPED, None, None, None)).await?;
+        tx.commit().await?;
+        println!(
+            "[{:?}] {} was announced recently, not announcing again.",
+            bot.bot_type(),
+            flight.ident
+        );
+        return Ok(());
+    }
+
+    // The ranking and its announcement are committed together; the delivery worker posts it
+    // to every network, and every webhook, separately, so one being down doesn't hold up the
+    // others.
+    let announcement_id = next_announcement_id(&mut *tx).await?;
+    let webhook_keys: Vec<String> = context
+        .webhooks
+        .iter()
+        .flat_map(|webhooks| webhooks.targets.iter().map(WebhookTarget::key))
+        .collect();
+    for provider in &providers {
+        let targets = match provider {
+            AuthProvider::WEBHOOK => webhook_keys.iter().map(|key| Some(key.as_str())).collect(),
+            _ => vec![None],
+        };
+
+        for target in targets {
+            let mut pending = post(
+                PostStatus::PENDING,
+                Some(provider.clone()),
+                target,
+                Some(announcement_id),
+            );
+            if *provider == AuthProvider::X {
+                pending.track = track.as_deref();
+            }
+
+            record_post(&mut *tx, pending).await?;
+        }
+    }
+    tx.commit().await?;
+
+    println!(
+        "[{:?}] Queued announcement of {} for {:?}.",
+        bot.bot_type(),
+        flight.ident,
+        providers
+    );
+
+    Ok(())
+}
+
+/// Fetches the track of the announced leg for its map, within the AeroAPI budget.
+///
+/// The announcement goes out without a map if the leg isn't known or the track can't be had.
+async fn fetch_track<B: RankingBot>(
+    context: &JobContext<B>,
+    aero_api: &AeroApi,
+    flight: &Flight,
+) -> Option<Vec<TrackPoint>> {
+    let bot_type = context.bot.bot_type();
+    let fa_flight_id = flight.fa_flight_id.as_deref()?;
+    let config = &context.config;
+
+    let reservation = reserve_pages(
+        &context.pool,
+        config.aero_api_monthly_budget,
+        config.aero_api_result_set_cost,
+        1,
+        Utc::now(),
+    )
+    .await;
+
+    let reservation = match reservation {
+        Ok(reservation) if reservation.pages > 0 => reservation,
+        Ok(_) => {
+            println!(
+                "[{:?}] AeroAPI budget exhausted, announcing {} without a map.",
+                bot_type, flight.ident
+            );
+            return None;
+        }
+        Err(e) => {
+            eprintln!(
+                "[{:?}] Couldn't reserve the track of {}: {}",
+                bot_type, flight.ident, e
+            );
+            return None;
+        }
+    };
+
+    let fetched_before = aero_api.pages_fetched();
+    let track = aero_api.flight_track(fa_flight_id).await;
+
+    if let Err(e) = settle(
+        &context.pool,
+        &reservation,
+        aero_api.pages_fetched() - fetched_before,
+    )
+    .await
+    {
+        eprintln!(
+            "[{:?}] Couldn't book the track of {}: {}",
+            bot_type, flight.ident, e
+        );
+    }
+
+    match track {
+        Ok(track) if !track.is_empty() => Some(track),
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!(
+                "[{:?}] Couldn't fetch the track of {}, announcing without a map: {}",
+                bot_type, flight.ident, e
+            );
+            None
+        }
+    }
+}
+
+/// Stores the positions of a search and drops the ones past retention.
+async fn archive_observations<B: RankingBot>(
+    context: &JobContext<B>,
+    observations: &[Observation],
+) -> Result<(), sqlx::Error> {
+    record_observations(&context.pool, observations).await?;
+
+    let retention = Duration::days(context.config.observation_retention_days.into());
+    prune_observations(&context.pool, Utc::now() - retention).await?;
+
+    Ok(())
+}
+
+/// Makes `flights` the stored ranking of `bot_type`.
+///
+/// Upserting before deleting the rest keeps the statement safe to repeat, and running it in
+/// the caller's transaction means a crash never leaves a ranking half-written.
+async fn replace_ranking(
+    tx: &mut Transaction<'_, Postgres>,
+    bot_type: &BotType,
+    flights: &[Flight],
+) -> Result<(), sqlx::Error> {
+    for f in flights {
+        sqlx::query("INSERT INTO Flights (ident, fa_flight_id, ranking, altitude, groundspeed, origin, destination, aircraft_type, position_time) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) \
+            ON CONFLICT (ranking, ident) DO UPDATE SET fa_flight_id = EXCLUDED.fa_flight_id, altitude = EXCLUDED.altitude, groundspeed = EXCLUDED.groundspeed, origin = EXCLUDED.origin, destination = EXCLUDED.destination, aircraft_type = EXCLUDED.aircraft_type, position_time = EXCLUDED.position_time;")
+            .bind(&f.ident)
+            .bind(&f.fa_flight_id)
+            .bind(&f.ranking)
+            .bind(f.altitude)
+            .bind(f.groundspeed)
+            .bind(&f.origin)
+            .bind(&f.destination)
+            .bind(&f.aircraft_type)
+            .bind(f.position_time)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    let idents: Vec<&str> = flights.iter().map(|f| f.ident.as_str()).collect();
+
+    sqlx::query(
+        "DELETE FROM Flights WHERE Flights.ranking = $1 AND NOT (Flights.ident = ANY($2));",
+    )
+    .bind(bot_type)
+    .bind(&idents)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use chrono::TimeZone;
+    use warp::http::StatusCode;
+
+    use crate::apis::WebhookFormat;
+
+    use super::*;
+    use crate::{
+        bots::{delivery_job, AltitudeBot, Deliverer, GroundspeedBot},
+        posts::{recent_posts, times_posted},
+        test_support::{
+            context, context_with, insert_bluesky_session, insert_mastodon_session, insert_session,
+            valid_expiry, MockAeroApi, MockBluesky, MockMastodon, MockWebhook, MockX,
+        },
+    };
+
+    const SEARCH_ALTITUDE: &str = include_str!("../../tests/fixtures/aeroapi/search_altitude.json");
+    const SEARCH_PAGINATED: &str =
+        include_str!("../../tests/fixtures/aeroapi/search_paginated.json");
+    const SEARCH_PAGINATED_LAST: &str =
+        include_str!("../../tests/fixtures/aeroapi/search_paginated_last.json");
+    const TRACK: &str = include_str!("../../tests/fixtures/aeroapi/track_N650GD.json");
+    const LEADER_ID: &str = "N650GD-1717160000-adhoc-0001";
+
+    /// Runs the ranking job and delivers what it queued.
+    async fn tick<B: RankingBot>(context: Data<JobContext<B>>) -> Result<(), BotError> {
+        ranking_job(Checker::default(), context.clone()).await?;
+        delivery_job(Deliverer::default(), context).await
+    }
+
+    /// Result sets and cost booked this month.
+    async fn usage(pool: &PgPool) -> (i32, i64) {
+        sqlx::query_as("SELECT result_sets, cost FROM AeroApiUsage;")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn ranked_idents(pool: &PgPool, bot_type: BotType) -> Vec<String> {
+        sqlx::query_scalar("SELECT ident FROM Flights WHERE ranking = $1 ORDER BY ident;")
+            .bind(bot_type)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn job_types_are_unique_per_bot() {
+        let names = HashSet::from([
+            Checker::<AltitudeBot>::NAME,
+            Checker::<GroundspeedBot>::NAME,
+            Deliverer::<AltitudeBot>::NAME,
+            Deliverer::<GroundspeedBot>::NAME,
+        ]);
+
+        assert_eq!(names.len(), 4);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn tweets_new_leader_and_stores_ranking(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        tick(context(AltitudeBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            x.tweets(),
+            ["Current highest flight: N650GD\n\
+            Altitude: 51000ft (15544.80m)\n\
+            Groundspeed: N/A\n\
+            Origin: Teterboro, Teterboro [KTEB]\n\
+            Destination: Unknown\n\n\
+            More info:\nhttps://www.flightaware.com/live/flight/id/N650GD-1717160000-adhoc-0001"]
+        );
+        // The balloon is filtered out.
+        assert_eq!(
+            ranked_idents(&pool, BotType::ALTITUDE).await,
+            ["N650GD", "UAE215"]
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn attaches_map_of_the_leaders_track(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        aero_api.add_track(LEADER_ID, TRACK);
+        let x = MockX::start();
+        let mastodon = MockMastodon::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        insert_mastodon_session(&pool, BotType::ALTITUDE).await;
+
+        tick(context_with(AltitudeBot, &pool, &aero_api, &x, |c| {
+            c.mastodon = Some(mastodon.credentials())
+        }))
+        .await
+        .unwrap();
+
+        let media = &x.tweet_media()[0];
+        assert_eq!(media.len(), 1);
+        assert_eq!(media[0].media_type, "image/png");
+        assert!(media[0].bytes.starts_with(b"\x89PNG"));
+        assert_eq!(
+            media[0].alt_text.as_deref(),
+            Some("Map of the track of N650GD up to its last reported position.")
+        );
+        // The track is billed like a search page.
+        assert_eq!(usage(&pool).await, (2, 10_000));
+
+        // Only the X row carries the track.
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        for post in posts {
+            let track = post.track.map(|track| track.0.len());
+            match post.provider {
+                Some(AuthProvider::X) => assert_eq!(track, Some(4)),
+                _ => assert_eq!(track, None),
+            }
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn tweets_text_only_without_track(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        tick(context(AltitudeBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+
+        // AeroAPI doesn't know the leg's track, and didn't bill for it.
+        assert_eq!(x.tweets().len(), 1);
+        assert!(x.tweet_media()[0].is_empty());
+        assert_eq!(usage(&pool).await, (1, 5_000));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn tweets_text_only_if_map_upload_fails(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        aero_api.add_track(LEADER_ID, TRACK);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        x.fail_next_upload(StatusCode::FORBIDDEN);
+
+        tick(context(AltitudeBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+
+        assert_eq!(x.tweets().len(), 1);
+        assert!(x.tweet_media()[0].is_empty());
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts[0].status, PostStatus::POSTED);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn skips_map_beyond_budget(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        aero_api.add_track(LEADER_ID, TRACK);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        // Covers the search, but not the track.
+        tick(context_with(AltitudeBot, &pool, &aero_api, &x, |c| {
+            c.config.aero_api_monthly_budget = Some(7_000)
+        }))
+        .await
+        .unwrap();
+
+        assert_eq!(x.tweets().len(), 1);
+        assert!(x.tweet_media()[0].is_empty());
+        assert_eq!(usage(&pool).await, (1, 5_000));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn archives_search_results_and_prunes_expired_ones(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        sqlx::query(
+            "INSERT INTO Observations (bot_type, ident, fetched_at) VALUES ($1, 'N650GD', $2);",
+        )
+        .bind(BotType::ALTITUDE)
+        .bind(Utc::now() - Duration::days(91))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        tick(context(AltitudeBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+
+        // Every flight with a position is kept, including the ones that aren't ranked.
+        let idents: Vec<String> =
+            sqlx::query_scalar("SELECT ident FROM Observations ORDER BY ident;")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(idents, ["N650GD", "UAE215"]);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn same_aircraft_can_lead_both_rankings(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        insert_session(&pool, BotType::GROUNDSPEED, valid_expiry()).await;
+
+        tick(context(AltitudeBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+        tick(context(GroundspeedBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+
+        assert_eq!(x.tweets().len(), 2);
+        assert_eq!(
+            ranked_idents(&pool, BotType::ALTITUDE).await,
+            ["N650GD", "UAE215"]
+        );
+        assert_eq!(ranked_idents(&pool, BotType::GROUNDSPEED).await, ["UAE215"]);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn replacing_ranking_is_idempotent(pool: PgPool) {
+        let flight = |ident: &str, altitude| Flight {
+            ident: ident.to_string(),
+            fa_flight_id: None,
+            ranking: BotType::ALTITUDE,
+            altitude: Some(altitude),
+            groundspeed: None,
+            origin: None,
+            aircraft_type: None,
+            destination: None,
+            position_time: None,
+        };
+
+        let mut tx = pool.begin().await.unwrap();
+        replace_ranking(
+            &mut tx,
+            &BotType::ALTITUDE,
+            &[flight("N650GD", 510), flight("UAE215", 470)],
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+
+        for _ in 0..2 {
+            let mut tx = pool.begin().await.unwrap();
+            replace_ranking(
+                &mut tx,
+                &BotType::ALTITUDE,
+                &[flight("N650GD", 512), flight("DAL40", 480)],
+            )
+            .await
+            .unwrap();
+            tx.commit().await.unwrap();
+        }
+
+        assert_eq!(
+            ranked_idents(&pool, BotType::ALTITUDE).await,
+            ["DAL40", "N650GD"]
+        );
+
+        let altitude: Option<i32> =
+            sqlx::query_scalar("SELECT altitude FROM Flights WHERE ident = 'N650GD';")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(altitude, Some(512));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn does_not_repeat_unchanged_leader(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        for _ in 0..2 {
+            tick(context(AltitudeBot, &pool, &aero_api, &x))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(x.tweets().len(), 1);
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        let statuses: Vec<_> = posts.iter().map(|p| p.status.clone()).collect();
+        assert_eq!(statuses, [PostStatus::SKIPPED, PostStatus::POSTED]);
+        assert_eq!(posts[1].external_id.as_deref(), Some("1"));
+        assert_eq!(
+            times_posted(&pool, &BotType::ALTITUDE, "N650GD")
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn does_not_retweet_recently_posted_leader(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        let flight = Flight {
+            ident: "N650GD".to_string(),
+            fa_flight_id: Some("N650GD-1717160000-adhoc-0001".to_string()),
+            ranking: BotType::ALTITUDE,
+            altitude: Some(510),
+            groundspeed: None,
+            origin: None,
+            aircraft_type: None,
+            destination: None,
+            position_time: None,
+        };
+        record_post(
+            &pool,
+            NewPost {
+                bot_type: BotType::ALTITUDE,
+                flight: &flight,
+                text: "Current highest flight: N650GD",
+                status: PostStatus::POSTED,
+                provider: Some(AuthProvider::X),
+                target: None,
+                announcement_id: None,
+                track: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        tick(context(AltitudeBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+
+        assert!(x.tweets().is_empty());
+        assert_eq!(
+            ranked_idents(&pool, BotType::ALTITUDE).await,
+            ["N650GD", "UAE215"]
+        );
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 1).await.unwrap();
+        assert_eq!(posts[0].status, PostStatus::SKIPPED);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn tweets_new_leg_of_reused_callsign(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        // Yesterday's leg of the same callsign still leads the stored ranking.
+        let yesterday = Flight {
+            ident: "N650GD".to_string(),
+            fa_flight_id: Some("N650GD-1717070000-adhoc-0001".to_string()),
+            ranking: BotType::ALTITUDE,
+            altitude: Some(520),
+            groundspeed: None,
+            origin: None,
+            aircraft_type: None,
+            destination: None,
+            position_time: None,
+        };
+        let mut tx = pool.begin().await.unwrap();
+        replace_ranking(
+            &mut tx,
+            &BotType::ALTITUDE,
+            std::slice::from_ref(&yesterday),
+        )
+        .await
+        .unwrap();
+        record_post(
+            &mut *tx,
+            NewPost {
+                bot_type: BotType::ALTITUDE,
+                flight: &yesterday,
+                text: "Current highest flight: N650GD",
+                status: PostStatus::POSTED,
+                provider: Some(AuthProvider::X),
+                target: None,
+                announcement_id: None,
+                track: None,
+            },
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+
+        tick(context(AltitudeBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+
+        assert_eq!(x.tweets().len(), 1);
+        assert!(x.tweets()[0].ends_with("/live/flight/id/N650GD-1717160000-adhoc-0001"));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn runs_when_schedule_is_due(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        let context = context(AltitudeBot, &pool, &aero_api, &x);
+        let start = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+
+        for (minutes, searches) in [(0, 1), (1, 1), (15 * 60, 1), (16 * 60, 2)] {
+            let tick = Checker::from(start + Duration::minutes(minutes));
+            ranking_job(tick, context.clone()).await.unwrap();
+            assert_eq!(aero_api.requests(), searches, "after {} minutes", minutes);
+        }
+
+        // Reloaded on the next tick.
+        sqlx::query("UPDATE Bots SET schedule = '0 */5 * * * * *' WHERE bot_type = $1;")
+            .bind(BotType::ALTITUDE)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let tick = Checker::from(start + Duration::minutes(16 * 60 + 5));
+        ranking_job(tick, context).await.unwrap();
+        assert_eq!(aero_api.requests(), 3);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn skips_disabled_bot(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        sqlx::query("INSERT INTO Bots (bot_type, enabled) VALUES ($1, FALSE);")
+            .bind(BotType::ALTITUDE)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        tick(context(AltitudeBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+
+        assert_eq!(aero_api.requests(), 0);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn follows_pagination_and_merges_pages(pool: PgPool) {
+        let aero_api =
+            MockAeroApi::start(SEARCH_PAGINATED, &[("7f9e3c21aa", SEARCH_PAGINATED_LAST)]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::GROUNDSPEED, valid_expiry()).await;
+
+        tick(context(GroundspeedBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+
+        assert_eq!(aero_api.requests(), 2);
+        assert!(x.tweets()[0].starts_with("Current fastest flight: DAL40\n"));
+        assert_eq!(
+            ranked_idents(&pool, BotType::GROUNDSPEED).await,
+            ["BAW286", "DAL40"]
+        );
+        assert_eq!(usage(&pool).await, (2, 10_000));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn bills_page_that_cannot_be_parsed(pool: PgPool) {
+        let aero_api = MockAeroApi::start("<html>Maintenance</html>", &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        let result = tick(context(AltitudeBot, &pool, &aero_api, &x)).await;
+
+        assert!(matches!(result, Err(BotError::AeroApiParse(_))));
+        assert_eq!(usage(&pool).await, (1, 5_000));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn degrades_to_pages_within_budget(pool: PgPool) {
+        let aero_api =
+            MockAeroApi::start(SEARCH_PAGINATED, &[("7f9e3c21aa", SEARCH_PAGINATED_LAST)]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::GROUNDSPEED, valid_expiry()).await;
+
+        let mut job_context = (*context(GroundspeedBot, &pool, &aero_api, &x)).clone();
+        job_context.config.aero_api_monthly_budget = Some(7_000);
+
+        tick(Data::new(job_context.clone())).await.unwrap();
+
+        assert_eq!(aero_api.requests(), 1);
+        assert_eq!(usage(&pool).await, (1, 5_000));
+
+        // The remaining $0.002 don't cover another page.
+        ranking_job(Checker::from(Utc::now()), Data::new(job_context))
+            .await
+            .unwrap();
+
+        assert_eq!(aero_api.requests(), 1);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn skips_unauthorized_bot_without_searching(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+
+        tick(context(AltitudeBot, &pool, &aero_api, &x))
+            .await
+            .unwrap();
+
+        assert_eq!(aero_api.requests(), 0);
+        assert!(x.tweets().is_empty());
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn queues_announcement_with_ranking(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        ranking_job(
+            Checker::default(),
+            context(AltitudeBot, &pool, &aero_api, &x),
+        )
+        .await
+        .unwrap();
+
+        // Nothing is tweeted until the delivery worker picks the announcement up.
+        assert!(x.tweets().is_empty());
+        assert_eq!(
+            ranked_idents(&pool, BotType::ALTITUDE).await,
+            ["N650GD", "UAE215"]
+        );
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].status, PostStatus::PENDING);
+        assert_eq!(posts[0].attempts, 0);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn announces_on_every_authorized_network(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        let mastodon = MockMastodon::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        insert_mastodon_session(&pool, BotType::ALTITUDE).await;
+
+        tick(context_with(AltitudeBot, &pool, &aero_api, &x, |c| {
+            c.mastodon = Some(mastodon.credentials())
+        }))
+        .await
+        .unwrap();
+
+        assert_eq!(x.tweets().len(), 1);
+        assert_eq!(mastodon.statuses(), x.tweets());
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts.len(), 2);
+        assert!(posts.iter().all(|p| p.status == PostStatus::POSTED));
+        assert_eq!(posts[0].announcement_id, posts[1].announcement_id);
+
+        // Retried deliveries reuse the key, so the instance drops them.
+        let mastodon_post = posts
+            .iter()
+            .find(|p| p.provider == Some(AuthProvider::MASTODON))
+            .unwrap();
+        assert_eq!(
+            mastodon.idempotency_keys(),
+            [Some(mastodon_post.id.to_string())]
+        );
+        assert_eq!(mastodon_post.external_id.as_deref(), Some("101"));
+
+        // Fanning out is still a single announcement.
+        assert_eq!(
+            times_posted(&pool, &BotType::ALTITUDE, "N650GD")
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn announces_on_bluesky_with_linked_flight(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        let bluesky = MockBluesky::start();
+        insert_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+        insert_bluesky_session(&pool, BotType::ALTITUDE, valid_expiry()).await;
+
+        tick(context_with(AltitudeBot, &pool, &aero_api, &x, |c| {
+            c.bluesky = Some(bluesky.credentials())
+        }))
+        .await
+        .unwrap();
+
+        let records = bluesky.records();
+        assert_eq!(records.len(), 1);
+        let url = "https://www.flightaware.com/live/flight/id/N650GD-1717160000-adhoc-0001";
+        // The same announcement, but with the link shortened like in the Bluesky app.
+        assert_eq!(
+            records[0]["text"],
+            x.tweets()[0].replace(url, "flightaware.com/live/f…")
+        );
+        assert_eq!(records[0]["facets"][0]["features"][0]["uri"], url);
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        let bluesky_post = posts
+            .iter()
+            .find(|p| p.provider == Some(AuthProvider::BLUESKY))
+            .unwrap();
+        assert_eq!(bluesky_post.status, PostStatus::POSTED);
+        assert!(bluesky_post
+            .external_id
+            .as_deref()
+            .unwrap()
+            .starts_with("at://"));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn announces_on_webhooks_without_onboarding(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        let webhook = MockWebhook::start();
+        let webhooks = webhook.config(vec![
+            webhook.target("json", WebhookFormat::Json),
+            webhook.target("slack", WebhookFormat::Slack),
+        ]);
+        let json_key = webhooks.targets[0].key();
+
+        tick(context_with(AltitudeBot, &pool, &aero_api, &x, |c| {
+            c.webhooks = Some(webhooks)
+        }))
+        .await
+        .unwrap();
+
+        // Not onboarded with X, but the webhooks still get the announcement.
+        assert!(x.tweets().is_empty());
+
+        let requests = webhook.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].body["ranking"], "ALTITUDE");
+        assert_eq!(requests[0].body["flight"]["ident"], "N650GD");
+        assert_eq!(requests[0].body["flight"]["altitude_ft"], 51000);
+        assert!(requests[0].body["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("Current highest flight: N650GD"));
+        assert_eq!(requests[1].body["blocks"][0]["text"]["text"], "N650GD");
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts.len(), 2);
+        assert!(posts
+            .iter()
+            .all(|p| p.provider == Some(AuthProvider::WEBHOOK) && p.status == PostStatus::POSTED));
+        assert_eq!(posts[1].target, Some(json_key));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn mastodon_only_bot_skips_x(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+        let mastodon = MockMastodon::start();
+        insert_mastodon_session(&pool, BotType::ALTITUDE).await;
+
+        tick(context_with(AltitudeBot, &pool, &aero_api, &x, |c| {
+            c.mastodon = Some(mastodon.credentials())
+        }))
+        .await
+        .unwrap();
+
+        assert!(x.tweets().is_empty());
+        assert_eq!(mastodon.statuses().len(), 1);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn dry_run_records_post_without_tweeting(pool: PgPool) {
+        let aero_api = MockAeroApi::start(SEARCH_ALTITUDE, &[]);
+        let x = MockX::start();
+
+        ranking_job(
+            Checker::default(),
+            context_with(AltitudeBot, &pool, &aero_api, &x, |c| {
+                c.settings.dry_run = true
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert!(x.tweets().is_empty());
+        assert!(ranked_idents(&pool, BotType::ALTITUDE).await.is_empty());
+
+        let posts = recent_posts(&pool, &BotType::ALTITUDE, 10).await.unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].ident, "N650GD");
+        assert!(posts[0]
+            .text
+            .starts_with("Current highest flight: N650GD\n"));
+        assert_eq!(posts[0].status, PostStatus::DRY_RUN);
+        assert_eq!(posts[0].flight.as_ref().unwrap().altitude, Some(510));
+    }
+}
@@ -0,0 +1,267 @@
+use std::str::FromStr;
+
+use apalis::cron::Schedule;
+use reqwest::Url;
+use shuttle_runtime::SecretStore;
+
+use crate::{
+    apis::{XEndpoints, DEFAULT_AERO_API_URL, DEFAULT_MAX_PAGES, DEFAULT_RESULT_SET_COST},
+    error::{require_secret, BotError},
+    templates::TweetTemplate,
+    types::BotConfig,
+};
+
+/// Days observations are kept unless configured otherwise.
+const DEFAULT_OBSERVATION_RETENTION_DAYS: u32 = 90;
+
+/// Settings shared by all bots, read from the secrets at startup.
+///
+/// The base URLs default to the production services and can be pointed at stand-in servers
+/// via `AERO_API_URL`, `X_API_URL`, `X_AUTH_URL`, `X_TOKEN_URL` and `X_REDIRECT_URL`.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub aero_api_url: String,
+    pub aero_api_key: String,
+    /// Pages fetched per AeroAPI search.
+    pub aero_api_max_pages: u32,
+    /// Price of an AeroAPI search result set in millionths of a dollar.
+    pub aero_api_result_set_cost: i64,
+    /// Monthly AeroAPI spend limit in millionths of a dollar, unlimited if unset.
+    pub aero_api_monthly_budget: Option<i64>,
+    /// Days search results are kept in the Observations table.
+    pub observation_retention_days: u32,
+    pub x_endpoints: XEndpoints,
+    /// Secret required to onboard a bot's accounts, onboarding is disabled without it.
+    pub onboarding_token: Option<String>,
+}
+
+impl Config {
+    pub fn from_secrets(secrets: &SecretStore) -> Result<Self, BotError> {
+        let x_defaults = XEndpoints::default();
+
+        let aero_api_max_pages =
+            parse_secret(secrets, "AERO_API_MAX_PAGES")?.unwrap_or(DEFAULT_MAX_PAGES);
+        let aero_api_result_set_cost =
+            dollar_secret(secrets, "AERO_API_RESULT_SET_COST")?.unwrap_or(DEFAULT_RESULT_SET_COST);
+        let aero_api_monthly_budget = dollar_secret(secrets, "AERO_API_MONTHLY_BUDGET")?;
+        let observation_retention_days = parse_secret(secrets, "OBSERVATION_RETENTION_DAYS")?
+            .unwrap_or(DEFAULT_OBSERVATION_RETENTION_DAYS);
+
+        Ok(Self {
+            aero_api_url: url_secret(secrets, "AERO_API_URL", DEFAULT_AERO_API_URL)?,
+            aero_api_key: require_secret(secrets, "AERO_API_KEY")?,
+            aero_api_max_pages,
+            aero_api_result_set_cost,
+            aero_api_monthly_budget,
+            observation_retention_days,
+            x_endpoints: XEndpoints {
+                api_url: url_secret(secrets, "X_API_URL", &x_defaults.api_url)?,
+                auth_url: url_secret(secrets, "X_AUTH_URL", &x_defaults.auth_url)?,
+                token_url: url_secret(secrets, "X_TOKEN_URL", &x_defaults.token_url)?,
+                redirect_url: url_secret(secrets, "X_REDIRECT_URL", &x_defaults.redirect_url)?,
+            },
+            onboarding_token: secrets
+                .get("ONBOARDING_TOKEN")
+                .filter(|token| !token.is_empty()),
+        })
+    }
+}
+
+/// When the bots run unless configured otherwise.
+pub const DEFAULT_SCHEDULE: &str = "0 0 */16 ? * * *";
+
+/// Settings of a single bot, read from the `{key}_*` secrets.
+///
+/// Schedule, threshold, the enabled flag and the template can be changed at runtime via the Bots table,
+/// see [`BotSettings::with_overrides`].
+#[derive(Clone, Debug)]
+pub struct BotSettings {
+    /// Render and record announcements without tweeting them.
+    pub dry_run: bool,
+    pub enabled: bool,
+    /// Lower bound of the ranked metric for AeroAPI to return a flight.
+    pub threshold: u32,
+    pub schedule: Schedule,
+    /// Renders the bot's announcements.
+    pub template: TweetTemplate,
+}
+
+impl BotSettings {
+    pub fn from_secrets(
+        secrets: &SecretStore,
+        key: &str,
+        default_threshold: u32,
+        default_template: &str,
+    ) -> Result<Self, BotError> {
+        let schedule_key = format!("{}_SCHEDULE", key);
+        let schedule = secrets
+            .get(&schedule_key)
+            .unwrap_or_else(|| DEFAULT_SCHEDULE.to_string());
+        let template_key = format!("{}_TEMPLATE", key);
+        let template = secrets
+            .get(&template_key)
+            .unwrap_or_else(|| default_template.to_string());
+
+        Ok(Self {
+            dry_run: parse_secret(secrets, &format!("{}_DRY_RUN", key))?.unwrap_or(false),
+            enabled: parse_secret(secrets, &format!("{}_ENABLED", key))?.unwrap_or(true),
+            threshold: parse_secret(secrets, &format!("{}_THRESHOLD", key))?
+                .unwrap_or(default_threshold),
+            schedule: parse_schedule(&schedule_key, &schedule)?,
+            template: parse_template(&template_key, &template)?,
+        })
+    }
+
+    /// Applies the settings stored for the bot in the Bots table.
+    pub fn with_overrides(&self, overrides: &BotConfig) -> Result<Self, BotError> {
+        let mut settings = self.clone();
+
+        if let Some(schedule) = &overrides.schedule {
+            settings.schedule = parse_schedule("Bots.schedule", schedule)?;
+        }
+
+        if let Some(threshold) = overrides.threshold {
+            settings.threshold = threshold
+                .try_into()
+                .map_err(|_| BotError::Config(format!("invalid Bots.threshold `{}`", threshold)))?;
+        }
+
+        if let Some(enabled) = overrides.enabled {
+            settings.enabled = enabled;
+        }
+
+        if let Some(template) = &overrides.template {
+            settings.template = parse_template("Bots.template", template)?;
+        }
+
+        Ok(settings)
+    }
+}
+
+fn parse_schedule(key: &str, schedule: &str) -> Result<Schedule, BotError> {
+    Schedule::from_str(schedule)
+        .map_err(|e| BotError::Config(format!("invalid {} `{}`: {}", key, schedule, e)))
+}
+
+/// Templates span lines, so unlike other values they're left out of the error.
+fn parse_template(key: &str, template: &str) -> Result<TweetTemplate, BotError> {
+    template
+        .parse()
+        .map_err(|e| BotError::Config(format!("invalid {}: {}", key, e)))
+}
+
+/// Reads an optional secret that has to parse as `T`.
+fn parse_secret<T: FromStr>(secrets: &SecretStore, key: &str) -> Result<Option<T>, BotError> {
+    secrets
+        .get(key)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| BotError::Config(format!("invalid {} `{}`", key, value)))
+        })
+        .transpose()
+}
+
+/// Reads an optional amount of dollars, e.g. `12.50`, as millionths of a dollar.
+fn dollar_secret(secrets: &SecretStore, key: &str) -> Result<Option<i64>, BotError> {
+    let Some(dollars) = parse_secret::<f64>(secrets, key)? else {
+        return Ok(None);
+    };
+
+    if !dollars.is_finite() || dollars < 0.0 {
+        return Err(BotError::Config(format!("invalid {} `{}`", key, dollars)));
+    }
+
+    Ok(Some((dollars * 1_000_000.0).round() as i64))
+}
+
+/// Reads an optional URL secret, falling back to `default`.
+fn url_secret(secrets: &SecretStore, key: &str, default: &str) -> Result<String, BotError> {
+    let url = secrets.get(key).unwrap_or_else(|| default.to_string());
+
+    Url::parse(&url).map_err(|e| BotError::Config(format!("invalid {} `{}`: {}", key, url, e)))?;
+
+    Ok(url.trim_end_matches('/').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BotType;
+
+    fn settings() -> BotSettings {
+        BotSettings {
+            dry_run: false,
+            enabled: true,
+            threshold: 450,
+            schedule: DEFAULT_SCHEDULE.parse().unwrap(),
+            template: "Current highest flight: {{ident}}".parse().unwrap(),
+        }
+    }
+
+    fn overrides() -> BotConfig {
+        BotConfig {
+            bot_type: BotType::ALTITUDE,
+            schedule: None,
+            threshold: None,
+            enabled: None,
+            template: None,
+            last_run_at: None,
+        }
+    }
+
+    #[test]
+    fn unset_overrides_keep_settings() {
+        let settings = settings().with_overrides(&overrides()).unwrap();
+
+        assert!(settings.enabled);
+        assert_eq!(settings.threshold, 450);
+        assert_eq!(settings.schedule.to_string(), DEFAULT_SCHEDULE);
+        assert_eq!(
+            settings.template,
+            "Current highest flight: {{ident}}".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn applies_overrides() {
+        let settings = settings()
+            .with_overrides(&BotConfig {
+                schedule: Some("0 0 */4 * * * *".to_string()),
+                threshold: Some(480),
+                enabled: Some(false),
+                template: Some("Highest: {{ident}} at {{altitude_fl}}".to_string()),
+                ..overrides()
+            })
+            .unwrap();
+
+        assert!(!settings.enabled);
+        assert_eq!(
+            settings.template,
+            "Highest: {{ident}} at {{altitude_fl}}".parse().unwrap()
+        );
+        assert_eq!(settings.threshold, 480);
+        assert_eq!(settings.schedule.to_string(), "0 0 */4 * * * *");
+    }
+
+    #[test]
+    fn rejects_invalid_overrides() {
+        let schedule = settings().with_overrides(&BotConfig {
+            schedule: Some("every 4 hours".to_string()),
+            ..overrides()
+        });
+        assert!(matches!(schedule, Err(BotError::Config(_))));
+
+        let threshold = settings().with_overrides(&BotConfig {
+            threshold: Some(-1),
+            ..overrides()
+        });
+        assert!(matches!(threshold, Err(BotError::Config(_))));
+
+        let template = settings().with_overrides(&BotConfig {
+            template: Some("Highest: {{callsign}}".to_string()),
+            ..overrides()
+        });
+        assert!(matches!(template, Err(BotError::Config(_))));
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use shuttle_runtime::SecretStore;
+
+use crate::types::{AuthProvider, BotType};
+
+#[derive(Debug, thiserror::Error)]
+pub enum BotError {
+    #[error("configuration error: {0}")]
+    Config(String),
+
+    #[error("AeroAPI request failed: {0}")]
+    AeroApiHttp(#[source] reqwest::Error),
+
+    #[error("couldn't parse AeroAPI response: {0}")]
+    AeroApiParse(#[source] serde_json::Error),
+
+    #[error("{0:?} bot is not authorized with {1:?}")]
+    NotAuthorized(BotType, AuthProvider),
+
+    #[error("{0:?} authorization failed: {1}")]
+    Auth(AuthProvider, String),
+
+    #[error("{0:?} post failed: {1}")]
+    Publish(AuthProvider, #[source] PublishError),
+
+    #[error("couldn't render map: {0}")]
+    Render(String),
+
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Why a network rejected a post, deletion or media upload.
+#[derive(Debug, thiserror::Error)]
+pub enum PublishError {
+    #[error("duplicate content")]
+    Duplicate,
+
+    #[error("rate limited until {}", .reset.map_or("unknown".to_string(), |r| r.to_rfc3339()))]
+    RateLimited { reset: Option<DateTime<Utc>> },
+
+    #[error("unauthorized")]
+    Unauthorized,
+
+    #[error("{0} is not supported")]
+    Unsupported(&'static str),
+
+    #[error("media processing failed: {0}")]
+    MediaProcessing(String),
+
+    #[error("media upload failed: {0}")]
+    MediaUpload(#[source] Box<PublishError>),
+
+    #[error("unexpected response {status}: {body}")]
+    Other { status: StatusCode, body: String },
+
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+}
+
+impl BotError {
+    /// Marks a rejected request as part of a media upload, so the caller can post without it.
+    pub fn into_media_upload(self) -> Self {
+        match self {
+            BotError::Publish(provider, e) => {
+                BotError::Publish(provider, PublishError::MediaUpload(Box::new(e)))
+            }
+            e => e,
+        }
+    }
+}
+
+/// Reads a secret that the bots can't run without.
+pub fn require_secret(secrets: &SecretStore, key: &str) -> Result<String, BotError> {
+    secrets
+        .get(key)
+        .ok_or_else(|| BotError::Config(format!("missing secret `{}`", key)))
+}
@@ -1,13 +1,26 @@
+// Enum variants mirror the Postgres enum labels.
+#![allow(clippy::upper_case_acronyms)]
+
 use bot_service::BotService;
 use shuttle_runtime::SecretStore;
 use sqlx::PgPool;
 
-mod altitude_bot;
 mod apis;
+mod bot_configs;
 mod bot_service;
-mod groundspeed_bot;
+mod bots;
+mod config;
+mod error;
+mod map;
+mod observations;
+mod posts;
+mod ranking;
+mod routes;
+mod templates;
+#[cfg(test)]
+mod test_support;
 mod types;
-mod utils;
+mod usage;
 
 #[shuttle_runtime::main]
 async fn shuttle_main(
@@ -0,0 +1,295 @@
+use tiny_skia::{
+    Color, FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, Stroke, Transform,
+};
+
+use crate::{
+    apis::Media,
+    error::BotError,
+    types::{Flight, TrackPoint},
+};
+
+/// Size of the rendered map, X's preferred 16:9 for a single image.
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 675;
+
+/// Degrees shown around a track at least, so a single position isn't zoomed to a point.
+const MIN_SPAN: f64 = 6.0;
+/// Share of the map left free around the track.
+const PADDING: f64 = 0.2;
+/// Graticule steps in degrees, the finest one drawing at most `MAX_GRID_LINES` lines per axis.
+const GRID_STEPS: [f64; 8] = [1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 45.0, 90.0];
+const MAX_GRID_LINES: f64 = 8.0;
+
+const BACKGROUND: (u8, u8, u8) = (11, 30, 51);
+const GRID: (u8, u8, u8, u8) = (255, 255, 255, 48);
+const TRACK: (u8, u8, u8) = (245, 166, 35);
+
+/// Renders the map of a flight's track as an image to attach to its announcement.
+pub fn track_map(flight: &Flight, track: &[TrackPoint]) -> Result<Media, BotError> {
+    Ok(Media {
+        bytes: render_track(track)?,
+        mime_type: "image/png".to_string(),
+        alt_text: Some(format!(
+            "Map of the track of {} up to its last reported position.",
+            flight.ident
+        )),
+    })
+}
+
+/// Draws the track on a graticule, ending in a marker pointing along the last heading, as PNG.
+pub fn render_track(track: &[TrackPoint]) -> Result<Vec<u8>, BotError> {
+    draw(track)?
+        .encode_png()
+        .map_err(|e| BotError::Render(e.to_string()))
+}
+
+fn draw(track: &[TrackPoint]) -> Result<Pixmap, BotError> {
+    let last = track
+        .last()
+        .ok_or_else(|| BotError::Render("no positions to draw".to_string()))?;
+    let mut pixmap = Pixmap::new(WIDTH, HEIGHT)
+        .ok_or_else(|| BotError::Render("couldn't allocate the map".to_string()))?;
+    pixmap.fill(Color::from_rgba8(
+        BACKGROUND.0,
+        BACKGROUND.1,
+        BACKGROUND.2,
+        255,
+    ));
+
+    let points = unwrap_longitudes(track);
+    let viewport = Viewport::fit(&points);
+
+    draw_graticule(&mut pixmap, &viewport);
+
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(TRACK.0, TRACK.1, TRACK.2, 255);
+
+    if points.len() > 1 {
+        let mut path = PathBuilder::new();
+        for (i, &(latitude, longitude)) in points.iter().enumerate() {
+            let (x, y) = viewport.project(latitude, longitude);
+            if i == 0 {
+                path.move_to(x, y);
+            } else {
+                path.line_to(x, y);
+            }
+        }
+
+        if let Some(path) = path.finish() {
+            let stroke = Stroke {
+                width: 4.0,
+                line_cap: LineCap::Round,
+                line_join: LineJoin::Round,
+                ..Stroke::default()
+            };
+            pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+        }
+    }
+
+    let &(latitude, longitude) = points.last().unwrap();
+    let (x, y) = viewport.project(latitude, longitude);
+    let marker = match last.heading {
+        // An arrowhead pointing north, turned to the heading.
+        Some(heading) => {
+            let mut path = PathBuilder::new();
+            path.move_to(0.0, -16.0);
+            path.line_to(11.0, 12.0);
+            path.line_to(0.0, 6.0);
+            path.line_to(-11.0, 12.0);
+            path.close();
+            path.finish()
+                .map(|path| (path, Transform::from_rotate(heading as f32)))
+        }
+        None => PathBuilder::from_circle(0.0, 0.0, 10.0).map(|path| (path, Transform::identity())),
+    };
+
+    if let Some((path, rotation)) = marker {
+        let transform = rotation.post_translate(x, y);
+        pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
+
+        let mut outline = Paint::default();
+        outline.set_color_rgba8(255, 255, 255, 255);
+        let stroke = Stroke {
+            width: 2.5,
+            line_join: LineJoin::Round,
+            ..Stroke::default()
+        };
+        pixmap.stroke_path(&path, &outline, &stroke, transform, None);
+    }
+
+    Ok(pixmap)
+}
+
+fn draw_graticule(pixmap: &mut Pixmap, viewport: &Viewport) {
+    let (north, west) = viewport.unproject(0.0, 0.0);
+    let (south, east) = viewport.unproject(WIDTH as f32, HEIGHT as f32);
+    let span = (east - west).max(north - south);
+    let step = GRID_STEPS
+        .into_iter()
+        .find(|step| span / step <= MAX_GRID_LINES)
+        .unwrap_or(90.0);
+
+    let mut path = PathBuilder::new();
+
+    let mut latitude = (south / step).ceil() * step;
+    while latitude <= north {
+        let (_, y) = viewport.project(latitude, west);
+        path.move_to(0.0, y);
+        path.line_to(WIDTH as f32, y);
+        latitude += step;
+    }
+
+    let mut longitude = (west / step).ceil() * step;
+    while longitude <= east {
+        let (x, _) = viewport.project(north, longitude);
+        path.move_to(x, 0.0);
+        path.line_to(x, HEIGHT as f32);
+        longitude += step;
+    }
+
+    if let Some(path) = path.finish() {
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(GRID.0, GRID.1, GRID.2, GRID.3);
+        pixmap.stroke_path(
+            &path,
+            &paint,
+            &Stroke::default(),
+            Transform::identity(),
+            None,
+        );
+    }
+}
+
+/// Latitudes and longitudes of the track, the longitudes continued past ±180° so a track
+/// crossing the antimeridian stays in one piece.
+fn unwrap_longitudes(track: &[TrackPoint]) -> Vec<(f64, f64)> {
+    let mut points: Vec<(f64, f64)> = Vec::with_capacity(track.len());
+
+    for point in track {
+        let mut longitude = point.longitude;
+        if let Some(&(_, previous)) = points.last() {
+            while longitude - previous > 180.0 {
+                longitude -= 360.0;
+            }
+            while longitude - previous < -180.0 {
+                longitude += 360.0;
+            }
+        }
+        points.push((point.latitude, longitude));
+    }
+
+    points
+}
+
+/// Equirectangular projection centered on the track, with longitudes shrunk by the cosine of
+/// its middle latitude so distances look right around it.
+#[derive(Debug)]
+struct Viewport {
+    center_latitude: f64,
+    center_longitude: f64,
+    longitude_scale: f64,
+    /// Pixels per degree of latitude.
+    zoom: f64,
+}
+
+impl Viewport {
+    fn fit(points: &[(f64, f64)]) -> Self {
+        let (mut south, mut north) = (f64::MAX, f64::MIN);
+        let (mut west, mut east) = (f64::MAX, f64::MIN);
+
+        for &(latitude, longitude) in points {
+            south = south.min(latitude);
+            north = north.max(latitude);
+            west = west.min(longitude);
+            east = east.max(longitude);
+        }
+
+        let center_latitude = (south + north) / 2.0;
+        let longitude_scale = center_latitude.to_radians().cos().max(0.1);
+        let width = ((east - west) * longitude_scale).max(MIN_SPAN) * (1.0 + 2.0 * PADDING);
+        let height = (north - south).max(MIN_SPAN) * (1.0 + 2.0 * PADDING);
+
+        Self {
+            center_latitude,
+            center_longitude: (west + east) / 2.0,
+            longitude_scale,
+            zoom: (WIDTH as f64 / width).min(HEIGHT as f64 / height),
+        }
+    }
+
+    fn project(&self, latitude: f64, longitude: f64) -> (f32, f32) {
+        let x = (longitude - self.center_longitude) * self.longitude_scale * self.zoom;
+        let y = (self.center_latitude - latitude) * self.zoom;
+
+        (
+            (x + WIDTH as f64 / 2.0) as f32,
+            (y + HEIGHT as f64 / 2.0) as f32,
+        )
+    }
+
+    fn unproject(&self, x: f32, y: f32) -> (f64, f64) {
+        let x = x as f64 - WIDTH as f64 / 2.0;
+        let y = y as f64 - HEIGHT as f64 / 2.0;
+
+        (
+            self.center_latitude - y / self.zoom,
+            self.center_longitude + x / (self.longitude_scale * self.zoom),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(latitude: f64, longitude: f64, heading: Option<i32>) -> TrackPoint {
+        TrackPoint {
+            latitude,
+            longitude,
+            heading,
+        }
+    }
+
+    #[test]
+    fn renders_png_of_x_size() {
+        let track = [point(62.9, -19.4, Some(30)), point(63.41, -18.72, Some(12))];
+
+        let png = render_track(&track).unwrap();
+
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), WIDTH);
+        assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), HEIGHT);
+    }
+
+    #[test]
+    fn marks_last_position() {
+        let track = [point(41.02, -60.11, None)];
+
+        let pixmap = draw(&track).unwrap();
+        let (x, y) = Viewport::fit(&unwrap_longitudes(&track)).project(41.02, -60.11);
+        let pixel = pixmap.pixel(x as u32, y as u32).unwrap();
+
+        assert_eq!(
+            (pixel.red(), pixel.green(), pixel.blue()),
+            (TRACK.0, TRACK.1, TRACK.2)
+        );
+    }
+
+    #[test]
+    fn keeps_track_across_antimeridian_together() {
+        let track = [point(52.0, 179.5, None), point(52.3, -179.5, None)];
+
+        let points = unwrap_longitudes(&track);
+
+        assert_eq!(points, vec![(52.0, 179.5), (52.3, 180.5)]);
+        let viewport = Viewport::fit(&points);
+        let (west, _) = viewport.project(52.0, 179.5);
+        let (east, _) = viewport.project(52.3, 180.5);
+        assert!(west < east && east - west < WIDTH as f32 / 2.0);
+    }
+
+    #[test]
+    fn fails_without_positions() {
+        assert!(matches!(render_track(&[]), Err(BotError::Render(_))));
+    }
+}
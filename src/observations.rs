@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, Postgres, QueryBuilder};
+
+use crate::types::Observation;
+
+/// Rows per `INSERT`, keeping the bind parameters below Postgres' limit of 65535.
+const BATCH_SIZE: usize = 1000;
+
+/// Stores the observations of a search, one multi-row `INSERT` per batch.
+pub async fn record_observations<'c>(
+    executor: impl PgExecutor<'c> + Copy,
+    observations: &[Observation],
+) -> Result<u64, sqlx::Error> {
+    let mut inserted = 0;
+
+    for batch in observations.chunks(BATCH_SIZE) {
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO Observations (bot_type, ident, fa_flight_id, altitude, groundspeed, latitude, longitude, heading, aircraft_type, position_time, fetched_at) ",
+        );
+
+        query.push_values(batch, |mut row, o| {
+            row.push_bind(&o.bot_type)
+                .push_bind(&o.ident)
+                .push_bind(&o.fa_flight_id)
+                .push_bind(o.altitude)
+                .push_bind(o.groundspeed)
+                .push_bind(o.latitude)
+                .push_bind(o.longitude)
+                .push_bind(o.heading)
+                .push_bind(&o.aircraft_type)
+                .push_bind(o.position_time)
+                .push_bind(o.fetched_at);
+        });
+
+        inserted += query.build().execute(executor).await?.rows_affected();
+    }
+
+    Ok(inserted)
+}
+
+/// Deletes the observations fetched before `cutoff`.
+pub async fn prune_observations(
+    executor: impl PgExecutor<'_>,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM Observations WHERE fetched_at < $1;")
+        .bind(cutoff)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected())
+}
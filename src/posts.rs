@@ -0,0 +1,199 @@
+use chrono::{DateTime, Utc};
+use sqlx::{types::Json, PgExecutor};
+
+use crate::types::{AuthProvider, BotType, Flight, Post, PostStatus, TrackPoint};
+
+/// An announcement to add to the post history.
+pub struct NewPost<'a> {
+    pub bot_type: BotType,
+    pub flight: &'a Flight,
+    pub text: &'a str,
+    pub status: PostStatus,
+    /// Network a pending announcement is delivered to.
+    pub provider: Option<AuthProvider>,
+    /// Webhook a pending `WEBHOOK` announcement is delivered to.
+    pub target: Option<&'a str>,
+    /// Shared by the rows of an announcement fanned out to several networks, see
+    /// [`next_announcement_id`]. A new one is drawn if missing.
+    pub announcement_id: Option<i64>,
+    /// Positions of the leg, for networks that attach a map.
+    pub track: Option<&'a [TrackPoint]>,
+}
+
+pub async fn record_post(
+    executor: impl PgExecutor<'_>,
+    post: NewPost<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO Posts (bot_type, ident, fa_flight_id, text, flight, status, provider, target, announcement_id, track) \
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, COALESCE($9, nextval('announcement_ids')), $10);")
+    .bind(post.bot_type)
+    .bind(&post.flight.ident)
+    .bind(&post.flight.fa_flight_id)
+    .bind(post.text)
+    .bind(Json(post.flight))
+    .bind(post.status)
+    .bind(post.provider)
+    .bind(post.target)
+    .bind(post.announcement_id)
+    .bind(post.track.map(Json))
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Draws the id grouping the per-network rows of a new announcement.
+pub async fn next_announcement_id(executor: impl PgExecutor<'_>) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT nextval('announcement_ids');")
+        .fetch_one(executor)
+        .await
+}
+
+/// Networks with announcements of a bot due for delivery.
+pub async fn pending_providers(
+    executor: impl PgExecutor<'_>,
+    bot_type: &BotType,
+) -> Result<Vec<AuthProvider>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT DISTINCT provider FROM Posts WHERE bot_type = $1 AND status = 'PENDING' AND provider IS NOT NULL \
+        AND (next_attempt_at IS NULL OR next_attempt_at <= NOW()) ORDER BY provider;",
+    )
+    .bind(bot_type)
+    .fetch_all(executor)
+    .await
+}
+
+/// Locks the oldest announcement of a bot due on `provider` that no other delivery is working
+/// on. Announcements backing off after a failed attempt are left for later.
+pub async fn next_pending_post(
+    executor: impl PgExecutor<'_>,
+    bot_type: &BotType,
+    provider: &AuthProvider,
+) -> Result<Option<Post>, sqlx::Error> {
+    sqlx::query_as("SELECT * FROM Posts WHERE bot_type = $1 AND provider = $2 AND status = 'PENDING' \
+        AND (next_attempt_at IS NULL OR next_attempt_at <= NOW()) ORDER BY id LIMIT 1 FOR UPDATE SKIP LOCKED;")
+        .bind(bot_type)
+        .bind(provider)
+        .fetch_optional(executor)
+        .await
+}
+
+pub async fn mark_delivered(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+    external_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE Posts SET status = 'POSTED', external_id = $2, attempts = attempts + 1, error = NULL, delivered_at = NOW() WHERE id = $1;")
+        .bind(id)
+        .bind(external_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// The network's id of the latest earlier announcement of the same ident on the row's network,
+/// which a new announcement of it is threaded under.
+pub async fn thread_parent(
+    executor: impl PgExecutor<'_>,
+    post: &Post,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT external_id FROM Posts WHERE bot_type = $1 AND provider = $2 AND target IS NOT DISTINCT FROM $3 AND ident = $4 \
+        AND status = 'POSTED' AND external_id IS NOT NULL AND id < $5 ORDER BY id DESC LIMIT 1;",
+    )
+    .bind(&post.bot_type)
+    .bind(&post.provider)
+    .bind(&post.target)
+    .bind(&post.ident)
+    .bind(post.id)
+    .fetch_optional(executor)
+    .await
+}
+
+/// Counts a failed delivery attempt, holding the announcement back until `next_attempt_at` or
+/// giving it up after `max_attempts`.
+pub async fn record_failed_delivery(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+    error: &str,
+    max_attempts: i32,
+    next_attempt_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE Posts SET attempts = attempts + 1, error = $2, next_attempt_at = $4, \
+        status = CASE WHEN attempts + 1 >= $3 THEN 'FAILED'::PostStatus ELSE status END WHERE id = $1;")
+        .bind(id)
+        .bind(error)
+        .bind(max_attempts)
+        .bind(next_attempt_at)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Number of announcements of `ident` as the leader of a ranking, on any network.
+pub async fn times_posted(
+    executor: impl PgExecutor<'_>,
+    bot_type: &BotType,
+    ident: &str,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(DISTINCT announcement_id) FROM Posts WHERE bot_type = $1 AND ident = $2 AND status = 'POSTED';",
+    )
+    .bind(bot_type)
+    .bind(ident)
+    .fetch_one(executor)
+    .await
+}
+
+/// When `ident` was last announced as the leader of a ranking.
+pub async fn last_posted_at(
+    executor: impl PgExecutor<'_>,
+    bot_type: &BotType,
+    ident: &str,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT MAX(posted_at) FROM Posts WHERE bot_type = $1 AND ident = $2 AND status = 'POSTED';",
+    )
+    .bind(bot_type)
+    .bind(ident)
+    .fetch_one(executor)
+    .await
+}
+
+/// Whether the leg was announced or queued as the leader of a ranking after `since`.
+///
+/// Legs AeroAPI didn't identify are matched by their ident.
+pub async fn was_announced_since(
+    executor: impl PgExecutor<'_>,
+    bot_type: &BotType,
+    flight: &Flight,
+    since: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM Posts WHERE bot_type = $1 AND status IN ('POSTED', 'PENDING') AND posted_at > $4 \
+            AND CASE WHEN $2::VARCHAR IS NULL THEN ident = $3 ELSE fa_flight_id = $2 END);",
+    )
+    .bind(bot_type)
+    .bind(&flight.fa_flight_id)
+    .bind(&flight.ident)
+    .bind(since)
+    .fetch_one(executor)
+    .await
+}
+
+/// The latest history entries of a ranking, newest first.
+pub async fn recent_posts(
+    executor: impl PgExecutor<'_>,
+    bot_type: &BotType,
+    limit: i64,
+) -> Result<Vec<Post>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT * FROM Posts WHERE bot_type = $1 ORDER BY posted_at DESC, id DESC LIMIT $2;",
+    )
+    .bind(bot_type)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+}
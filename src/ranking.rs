@@ -0,0 +1,126 @@
+use std::cmp::Ordering;
+
+use crate::types::Flight;
+
+/// Number of flights kept per ranking.
+pub const RANKING_SIZE: usize = 3;
+
+/// Returns the `n` flights with the highest metric, best first.
+///
+/// Flights without a metric are dropped. Ties go to the flight with the more
+/// recent position, then to the alphabetically first ident.
+pub fn top_flights<F>(flights: Vec<Flight>, n: usize, metric: F) -> Vec<Flight>
+where
+    F: Fn(&Flight) -> Option<i32>,
+{
+    let mut ranked: Vec<(i32, Flight)> = flights
+        .into_iter()
+        .filter_map(|f| metric(&f).map(|value| (value, f)))
+        .collect();
+
+    ranked.sort_by(|(a_value, a), (b_value, b)| {
+        b_value
+            .cmp(a_value)
+            .then_with(|| compare_position_time(a, b))
+            .then_with(|| a.ident.cmp(&b.ident))
+    });
+    ranked.truncate(n);
+
+    ranked.into_iter().map(|(_, f)| f).collect()
+}
+
+/// Orders more recent positions first and missing positions last.
+fn compare_position_time(a: &Flight, b: &Flight) -> Ordering {
+    match (a.position_time, b.position_time) {
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::types::BotType;
+
+    fn flight(ident: &str, altitude: Option<i32>, position_minute: Option<u32>) -> Flight {
+        Flight {
+            ident: ident.to_string(),
+            fa_flight_id: None,
+            ranking: BotType::ALTITUDE,
+            altitude,
+            groundspeed: None,
+            destination: None,
+            origin: None,
+            aircraft_type: None,
+            position_time: position_minute
+                .map(|m| Utc.with_ymd_and_hms(2024, 6, 1, 12, m, 0).unwrap()),
+        }
+    }
+
+    fn idents(flights: &[Flight]) -> Vec<&str> {
+        flights.iter().map(|f| f.ident.as_str()).collect()
+    }
+
+    #[test]
+    fn ranks_highest_metric_first() {
+        let flights = vec![
+            flight("LOW", Some(451), Some(0)),
+            flight("HIGH", Some(510), Some(0)),
+            flight("MID", Some(470), Some(0)),
+            flight("LOWER", Some(450), Some(0)),
+        ];
+
+        let top = top_flights(flights, RANKING_SIZE, |f| f.altitude);
+
+        assert_eq!(idents(&top), ["HIGH", "MID", "LOW"]);
+    }
+
+    #[test]
+    fn drops_flights_without_metric() {
+        let flights = vec![
+            flight("NONE1", None, Some(0)),
+            flight("SOME", Some(460), Some(0)),
+            flight("NONE2", None, Some(0)),
+        ];
+
+        let top = top_flights(flights, RANKING_SIZE, |f| f.altitude);
+
+        assert_eq!(idents(&top), ["SOME"]);
+    }
+
+    #[test]
+    fn only_missing_metrics_yields_empty_ranking() {
+        let flights = vec![flight("NONE", None, Some(0))];
+
+        assert!(top_flights(flights, RANKING_SIZE, |f| f.altitude).is_empty());
+    }
+
+    #[test]
+    fn breaks_ties_by_most_recent_position() {
+        let flights = vec![
+            flight("OLD", Some(470), Some(1)),
+            flight("UNKNOWN", Some(470), None),
+            flight("NEW", Some(470), Some(5)),
+        ];
+
+        let top = top_flights(flights, RANKING_SIZE, |f| f.altitude);
+
+        assert_eq!(idents(&top), ["NEW", "OLD", "UNKNOWN"]);
+    }
+
+    #[test]
+    fn breaks_remaining_ties_by_ident() {
+        let flights = vec![
+            flight("BBB", Some(470), Some(0)),
+            flight("AAA", Some(470), Some(0)),
+        ];
+
+        let top = top_flights(flights, RANKING_SIZE, |f| f.altitude);
+
+        assert_eq!(idents(&top), ["AAA", "BBB"]);
+    }
+}
@@ -0,0 +1,897 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, Duration, Utc};
+use oauth2::{AuthorizationCode, PkceCodeVerifier};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, PgPool};
+use warp::{
+    http::{StatusCode, Uri},
+    reply::{self, Reply, Response},
+    Filter, Rejection,
+};
+
+use crate::{
+    apis::{BlueskyApi, BlueskyCredentials, MastodonApi, MastodonCredentials, XApi, XCredentials},
+    error::BotError,
+    posts::{last_posted_at, recent_posts, times_posted},
+    types::{AuthProvider, BotType, Flight, Post, PostStatus},
+};
+
+/// Entries returned by `GET /posts/{name}`.
+const RECENT_POSTS_LIMIT: i64 = 50;
+
+/// How long an authorization started via `/auth/{name}/start` can be completed.
+const AUTH_REQUEST_TTL: Duration = Duration::minutes(10);
+
+/// A bot that can be authorized with X via `/auth/{name}/start`, and with Mastodon or Bluesky
+/// via `/auth/{name}/{provider}/start` if it has them configured.
+#[derive(Clone, Debug)]
+pub struct AuthTarget {
+    pub bot_type: BotType,
+    pub credentials: XCredentials,
+    pub mastodon: Option<MastodonCredentials>,
+    pub bluesky: Option<BlueskyCredentials>,
+}
+
+#[derive(Clone)]
+struct AuthContext {
+    pool: PgPool,
+    targets: Arc<HashMap<String, AuthTarget>>,
+    /// Secret the owner passes to start onboarding, unset if onboarding is disabled.
+    onboarding_token: Option<Arc<str>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StartQuery {
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlueskyLogin {
+    identifier: String,
+    app_password: String,
+    #[serde(default)]
+    token: String,
+}
+
+/// OAuth onboarding routes, keyed by bot name.
+///
+/// `GET /auth/{name}/{provider}/start` redirects the account owner to the network, which sends
+/// them back to `GET /callback` once they granted access. `GET /auth/{name}/start` is short for
+/// the `x` provider.
+///
+/// Bluesky has no OAuth for bots: its start page asks for an app password, which is posted to
+/// `POST /auth/{name}/bluesky`.
+///
+/// Onboarding replaces the bot's account, so the start routes and the Bluesky login take the
+/// `onboarding_token` as `?token=`. Only authorizations started with it are accepted by the
+/// callback. Without a token onboarding is disabled.
+pub fn auth_routes(
+    pool: PgPool,
+    targets: HashMap<String, AuthTarget>,
+    onboarding_token: Option<String>,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let context = AuthContext {
+        pool,
+        targets: Arc::new(targets),
+        onboarding_token: onboarding_token.map(Arc::from),
+    };
+    let with_context = warp::any().map(move || context.clone());
+
+    let start_x = warp::path!("auth" / String / "start")
+        .and(warp::get())
+        .and(warp::query::<StartQuery>())
+        .and(with_context.clone())
+        .then(|name, query, context| start(name, "x".to_string(), query, context));
+
+    let start_provider = warp::path!("auth" / String / String / "start")
+        .and(warp::get())
+        .and(warp::query::<StartQuery>())
+        .and(with_context.clone())
+        .then(start);
+
+    let bluesky_login = warp::path!("auth" / String / "bluesky")
+        .and(warp::post())
+        .and(warp::body::form::<BlueskyLogin>())
+        .and(with_context.clone())
+        .then(bluesky_login);
+
+    let callback = warp::path!("callback")
+        .and(warp::get())
+        .and(warp::query::<CallbackQuery>())
+        .and(with_context)
+        .then(callback);
+
+    start_x
+        .or(start_provider)
+        .unify()
+        .or(bluesky_login)
+        .unify()
+        .or(callback)
+        .unify()
+}
+
+async fn start(
+    name: String,
+    provider: String,
+    query: StartQuery,
+    context: AuthContext,
+) -> Response {
+    let token = query.token.unwrap_or_default();
+    if let Some(response) = reject_onboarding(&context, &token) {
+        return response;
+    }
+
+    let Some(target) = context.targets.get(&name) else {
+        return page(StatusCode::NOT_FOUND, "Unknown bot.");
+    };
+
+    let provider = match provider.as_str() {
+        "x" => AuthProvider::X,
+        "mastodon" if target.mastodon.is_some() => AuthProvider::MASTODON,
+        "bluesky" if target.bluesky.is_some() => return bluesky_login_page(&name, &token),
+        _ => return page(StatusCode::NOT_FOUND, "Unknown network."),
+    };
+
+    match begin_authorization(target, provider, &context.pool).await {
+        Ok(url) => warp::redirect::found(url).into_response(),
+        Err(e) => {
+            eprintln!(
+                "[{:?}] Couldn't start authorization: {}",
+                target.bot_type, e
+            );
+            page(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Couldn't start the authorization.",
+            )
+        }
+    }
+}
+
+async fn begin_authorization(
+    target: &AuthTarget,
+    provider: AuthProvider,
+    pool: &PgPool,
+) -> Result<Uri, BotError> {
+    let (url, state, pkce_verifier) = match (&provider, &target.mastodon) {
+        (AuthProvider::MASTODON, Some(mastodon)) => {
+            MastodonApi::authorize_url(mastodon, pool).await?
+        }
+        _ => XApi::authorize_url(&target.credentials)?,
+    };
+
+    sqlx::query("DELETE FROM AuthRequests WHERE created_at < $1;")
+        .bind(Utc::now() - AUTH_REQUEST_TTL)
+        .execute(pool)
+        .await?;
+
+    sqlx::query("INSERT INTO AuthRequests (state, bot_type, pkce_verifier, provider) VALUES ($1, $2, $3, $4);")
+        .bind(state.secret())
+        .bind(&target.bot_type)
+        .bind(pkce_verifier.secret())
+        .bind(&provider)
+        .execute(pool)
+        .await?;
+
+    url.as_str()
+        .parse()
+        .map_err(|e| BotError::Auth(provider, format!("invalid authorization URL: {}", e)))
+}
+
+/// Refuses onboarding to anyone but the owner, and to everybody if no token is configured.
+fn reject_onboarding(context: &AuthContext, token: &str) -> Option<Response> {
+    let Some(expected) = &context.onboarding_token else {
+        return Some(page(
+            StatusCode::FORBIDDEN,
+            "Onboarding is disabled. Set the ONBOARDING_TOKEN secret to enable it.",
+        ));
+    };
+
+    // Compared in constant time, so the token can't be guessed byte by byte.
+    let matches = expected.len() == token.len()
+        && expected
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0;
+
+    if !matches {
+        eprintln!("Rejected onboarding request with invalid token.");
+        return Some(page(StatusCode::UNAUTHORIZED, "Invalid onboarding token."));
+    }
+
+    None
+}
+
+/// The login form carries the onboarding token along to the login.
+fn bluesky_login_page(name: &str, token: &str) -> Response {
+    let token = token
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;");
+
+    reply::html(format!(
+        "<!DOCTYPE html><form method=\"post\" action=\"/auth/{}/bluesky\">\
+        <input name=\"token\" type=\"hidden\" value=\"{}\">\
+        <p><label>Handle <input name=\"identifier\" required></label></p>\
+        <p><label>App password <input name=\"app_password\" type=\"password\" required></label></p>\
+        <p><button>Authorize</button></p></form>",
+        name, token
+    ))
+    .into_response()
+}
+
+async fn bluesky_login(name: String, login: BlueskyLogin, context: AuthContext) -> Response {
+    if let Some(response) = reject_onboarding(&context, &login.token) {
+        return response;
+    }
+
+    let Some(target) = context.targets.get(&name) else {
+        return page(StatusCode::NOT_FOUND, "Unknown bot.");
+    };
+    let Some(bluesky) = &target.bluesky else {
+        return page(StatusCode::NOT_FOUND, "Unknown network.");
+    };
+
+    let result = BlueskyApi::authorize(
+        bluesky,
+        target.bot_type.clone(),
+        &login.identifier,
+        &login.app_password,
+        &context.pool,
+    )
+    .await;
+
+    match result {
+        Ok(()) => {
+            println!("[{:?}] Authorized with BLUESKY.", target.bot_type);
+            page(
+                StatusCode::OK,
+                &format!("{:?} bot is now authorized.", target.bot_type),
+            )
+        }
+        Err(e) => {
+            eprintln!("[{:?}] Authorization failed: {}", target.bot_type, e);
+            page(
+                StatusCode::BAD_GATEWAY,
+                "Couldn't log in to Bluesky. Check the handle and app password.",
+            )
+        }
+    }
+}
+
+async fn callback(query: CallbackQuery, context: AuthContext) -> Response {
+    if let Some(error) = query.error {
+        eprintln!("Authorization was not granted: {}", error);
+        return page(StatusCode::BAD_REQUEST, "Authorization was not granted.");
+    }
+
+    let (Some(code), Some(state)) = (query.code, query.state) else {
+        return page(StatusCode::BAD_REQUEST, "Missing code or state.");
+    };
+
+    // Each state can only be redeemed once.
+    let request: Option<(BotType, AuthProvider, String, DateTime<Utc>)> = match sqlx::query_as(
+        "DELETE FROM AuthRequests WHERE state = $1 RETURNING bot_type, provider, pkce_verifier, created_at;",
+    )
+    .bind(&state)
+    .fetch_optional(&context.pool)
+    .await
+    {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("Couldn't look up authorization state: {}", e);
+            return page(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Couldn't complete the authorization.",
+            );
+        }
+    };
+
+    let Some((bot_type, provider, pkce_verifier, created_at)) = request else {
+        eprintln!("Rejected callback with unknown state.");
+        return page(
+            StatusCode::BAD_REQUEST,
+            "This authorization link is invalid or was already used. Please start over.",
+        );
+    };
+
+    if created_at + AUTH_REQUEST_TTL < Utc::now() {
+        eprintln!("[{:?}] Rejected callback with expired state.", bot_type);
+        return page(
+            StatusCode::GONE,
+            "This authorization link has expired. Please start over.",
+        );
+    }
+
+    let Some(target) = context.targets.values().find(|t| t.bot_type == bot_type) else {
+        return page(StatusCode::NOT_FOUND, "This bot is no longer available.");
+    };
+
+    let code = AuthorizationCode::new(code);
+    let pkce_verifier = PkceCodeVerifier::new(pkce_verifier);
+
+    let result = match (&provider, &target.mastodon) {
+        (AuthProvider::X, _) => {
+            XApi::authorize(
+                &target.credentials,
+                bot_type.clone(),
+                code,
+                pkce_verifier,
+                &context.pool,
+            )
+            .await
+        }
+        (AuthProvider::MASTODON, Some(mastodon)) => {
+            MastodonApi::authorize(
+                mastodon,
+                bot_type.clone(),
+                code,
+                pkce_verifier,
+                &context.pool,
+            )
+            .await
+        }
+        (AuthProvider::MASTODON, None) => {
+            return page(
+                StatusCode::NOT_FOUND,
+                "This bot no longer posts to Mastodon.",
+            );
+        }
+        // Bluesky logins never go through the callback, webhooks need no authorization.
+        (AuthProvider::BLUESKY | AuthProvider::WEBHOOK, _) => {
+            return page(StatusCode::BAD_REQUEST, "Missing code or state.");
+        }
+    };
+
+    match result {
+        Ok(()) => {
+            println!("[{:?}] Authorized with {:?}.", bot_type, provider);
+            page(
+                StatusCode::OK,
+                &format!("{:?} bot is now authorized.", bot_type),
+            )
+        }
+        Err(e) => {
+            eprintln!("[{:?}] Authorization failed: {}", bot_type, e);
+            page(
+                StatusCode::BAD_GATEWAY,
+                &format!("Couldn't complete the authorization with {:?}.", provider),
+            )
+        }
+    }
+}
+
+/// A post as listed by `GET /posts/{name}`. Leaves out the webhook a row went to and the
+/// delivery error, which can name the networks' URLs.
+#[derive(Debug, Serialize)]
+struct PublicPost {
+    ident: String,
+    fa_flight_id: Option<String>,
+    provider: Option<AuthProvider>,
+    announcement_id: i64,
+    external_id: Option<String>,
+    text: String,
+    flight: Option<Json<Flight>>,
+    status: PostStatus,
+    posted_at: DateTime<Utc>,
+    attempts: i32,
+    delivered_at: Option<DateTime<Utc>>,
+}
+
+impl From<Post> for PublicPost {
+    fn from(post: Post) -> Self {
+        Self {
+            ident: post.ident,
+            fa_flight_id: post.fa_flight_id,
+            provider: post.provider,
+            announcement_id: post.announcement_id,
+            external_id: post.external_id,
+            text: post.text,
+            flight: post.flight,
+            status: post.status,
+            posted_at: post.posted_at,
+            attempts: post.attempts,
+            delivered_at: post.delivered_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct FlightHistory {
+    ident: String,
+    times_posted: i64,
+    last_posted_at: Option<DateTime<Utc>>,
+}
+
+/// Read-only post history routes, keyed by bot name.
+///
+/// `GET /posts/{name}` lists the latest job runs of a bot, `GET /posts/{name}/{ident}`
+/// tells how often and when a flight was last announced as the leader.
+pub fn history_routes(
+    pool: PgPool,
+    bots: HashMap<String, BotType>,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let bots = Arc::new(bots);
+    let with_bot = warp::any()
+        .map(move || (pool.clone(), bots.clone()))
+        .and(warp::path("posts"))
+        .and(warp::path::param::<String>())
+        .map(
+            |(pool, bots): (PgPool, Arc<HashMap<String, BotType>>), name: String| {
+                (pool, bots.get(&name).cloned())
+            },
+        );
+
+    let recent = with_bot
+        .clone()
+        .and(warp::path::end())
+        .and(warp::get())
+        .then(|(pool, bot_type)| recent(pool, bot_type));
+
+    let flight = with_bot
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .then(|(pool, bot_type), ident| flight_history(pool, bot_type, ident));
+
+    recent.or(flight).unify()
+}
+
+async fn recent(pool: PgPool, bot_type: Option<BotType>) -> Response {
+    let Some(bot_type) = bot_type else {
+        return page(StatusCode::NOT_FOUND, "Unknown bot.");
+    };
+
+    let posts: Result<Vec<Post>, _> = recent_posts(&pool, &bot_type, RECENT_POSTS_LIMIT).await;
+
+    match posts {
+        Ok(posts) => {
+            let posts: Vec<PublicPost> = posts.into_iter().map(PublicPost::from).collect();
+            reply::json(&posts).into_response()
+        }
+        Err(e) => {
+            eprintln!("[{:?}] Couldn't load post history: {}", bot_type, e);
+            page(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Couldn't load the post history.",
+            )
+        }
+    }
+}
+
+async fn flight_history(pool: PgPool, bot_type: Option<BotType>, ident: String) -> Response {
+    let Some(bot_type) = bot_type else {
+        return page(StatusCode::NOT_FOUND, "Unknown bot.");
+    };
+
+    let history = async {
+        Ok::<_, sqlx::Error>(FlightHistory {
+            times_posted: times_posted(&pool, &bot_type, &ident).await?,
+            last_posted_at: last_posted_at(&pool, &bot_type, &ident).await?,
+            ident,
+        })
+    };
+
+    match history.await {
+        Ok(history) => reply::json(&history).into_response(),
+        Err(e) => {
+            eprintln!("[{:?}] Couldn't load flight history: {}", bot_type, e);
+            page(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Couldn't load the flight history.",
+            )
+        }
+    }
+}
+
+fn page(status: StatusCode, message: &str) -> Response {
+    reply::with_status(
+        reply::html(format!("<!DOCTYPE html><p>{}</p>", message)),
+        status,
+    )
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use warp::http::header::LOCATION;
+
+    use super::*;
+    use crate::{
+        test_support::{MockBluesky, MockMastodon, MockX, BLUESKY_DID},
+        types::Session,
+    };
+
+    const ONBOARDING_TOKEN: &str = "onboarding-token";
+
+    fn routes(pool: PgPool) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+        let targets = HashMap::from([(
+            "altitude".to_string(),
+            AuthTarget {
+                bot_type: BotType::ALTITUDE,
+                credentials: MockX::start().credentials(),
+                mastodon: None,
+                bluesky: None,
+            },
+        )]);
+
+        auth_routes(pool, targets, Some(ONBOARDING_TOKEN.to_string()))
+    }
+
+    async fn insert_request(pool: &PgPool, state: &str, created_at: DateTime<Utc>) {
+        sqlx::query(
+            "INSERT INTO AuthRequests (state, bot_type, pkce_verifier, created_at) VALUES ($1, $2, $3, $4);",
+        )
+        .bind(state)
+        .bind(BotType::ALTITUDE)
+        .bind("pkce-verifier")
+        .bind(created_at)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn access_token(pool: &PgPool) -> Option<String> {
+        sqlx::query_scalar("SELECT access_token FROM Sessions WHERE bot_type = $1;")
+            .bind(BotType::ALTITUDE)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn callback(
+        routes: &(impl Filter<Extract = (Response,), Error = Rejection> + Clone + 'static),
+        state: &str,
+    ) -> StatusCode {
+        warp::test::request()
+            .path(&format!("/callback?code=auth-code&state={}", state))
+            .reply(routes)
+            .await
+            .status()
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn start_stores_state_and_redirects(pool: PgPool) {
+        let routes = routes(pool.clone());
+
+        let response = warp::test::request()
+            .path(&format!("/auth/altitude/start?token={}", ONBOARDING_TOKEN))
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::FOUND);
+
+        let location = response.headers()[LOCATION].to_str().unwrap();
+        let location = reqwest::Url::parse(location).unwrap();
+        let state = location
+            .query_pairs()
+            .find(|(key, _)| key == "state")
+            .map(|(_, state)| state.into_owned())
+            .unwrap();
+
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM AuthRequests WHERE state = $1;")
+            .bind(&state)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, 1);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn start_rejects_unknown_bot(pool: PgPool) {
+        let response = warp::test::request()
+            .path(&format!("/auth/lowest/start?token={}", ONBOARDING_TOKEN))
+            .reply(&routes(pool.clone()))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // The bot has no Mastodon instance configured.
+        let response = warp::test::request()
+            .path(&format!(
+                "/auth/altitude/mastodon/start?token={}",
+                ONBOARDING_TOKEN
+            ))
+            .reply(&routes(pool))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn start_requires_onboarding_token(pool: PgPool) {
+        let routes = routes(pool.clone());
+
+        for path in ["/auth/altitude/start", "/auth/altitude/start?token=guess"] {
+            let response = warp::test::request().path(path).reply(&routes).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM AuthRequests;")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, 0);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn onboarding_is_disabled_without_token(pool: PgPool) {
+        let bluesky = MockBluesky::start();
+        let targets = HashMap::from([(
+            "altitude".to_string(),
+            AuthTarget {
+                bot_type: BotType::ALTITUDE,
+                credentials: MockX::start().credentials(),
+                mastodon: None,
+                bluesky: Some(bluesky.credentials()),
+            },
+        )]);
+        let routes = auth_routes(pool.clone(), targets, None);
+
+        let response = warp::test::request()
+            .path("/auth/altitude/start?token=")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/auth/altitude/bluesky")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("identifier=highestaircraft.bsky.social&app_password=app-password")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(bluesky.logins(), 0);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn bluesky_login_stores_session_and_app_password(pool: PgPool) {
+        let bluesky = MockBluesky::start();
+        let targets = HashMap::from([(
+            "altitude".to_string(),
+            AuthTarget {
+                bot_type: BotType::ALTITUDE,
+                credentials: MockX::start().credentials(),
+                mastodon: None,
+                bluesky: Some(bluesky.credentials()),
+            },
+        )]);
+        let routes = auth_routes(pool.clone(), targets, Some(ONBOARDING_TOKEN.to_string()));
+
+        let response = warp::test::request()
+            .path(&format!(
+                "/auth/altitude/bluesky/start?token={}",
+                ONBOARDING_TOKEN
+            ))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let form = String::from_utf8_lossy(response.body()).into_owned();
+        assert!(form.contains("action=\"/auth/altitude/bluesky\""));
+        assert!(form.contains("name=\"token\" type=\"hidden\" value=\"onboarding-token\""));
+
+        let login = |password: &str, token: &str| {
+            warp::test::request()
+                .method("POST")
+                .path("/auth/altitude/bluesky")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(format!(
+                    "identifier=highestaircraft.bsky.social&app_password={}&token={}",
+                    password, token
+                ))
+        };
+
+        let response = login("app-password", "guess").reply(&routes).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(bluesky.logins(), 0);
+
+        let response = login("wrong-password", ONBOARDING_TOKEN)
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(access_token(&pool).await, None);
+
+        let response = login("app-password", ONBOARDING_TOKEN).reply(&routes).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let session: Session =
+            sqlx::query_as("SELECT * FROM Sessions WHERE provider = 'BLUESKY' AND bot_type = $1;")
+                .bind(BotType::ALTITUDE)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(session.account_id.as_deref(), Some(BLUESKY_DID));
+        assert_eq!(session.app_password.as_deref(), Some("app-password"));
+        assert_eq!(session.refresh_token.as_deref(), Some("refresh-1"));
+        assert!(!session.is_expired(Duration::minutes(1)));
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn mastodon_onboarding_registers_app_and_stores_session(pool: PgPool) {
+        let mastodon = MockMastodon::start();
+        let targets = HashMap::from([(
+            "altitude".to_string(),
+            AuthTarget {
+                bot_type: BotType::ALTITUDE,
+                credentials: MockX::start().credentials(),
+                mastodon: Some(mastodon.credentials()),
+                bluesky: None,
+            },
+        )]);
+        let routes = auth_routes(pool.clone(), targets, Some(ONBOARDING_TOKEN.to_string()));
+
+        let mut states = vec![];
+        for _ in 0..2 {
+            let response = warp::test::request()
+                .path(&format!(
+                    "/auth/altitude/mastodon/start?token={}",
+                    ONBOARDING_TOKEN
+                ))
+                .reply(&routes)
+                .await;
+            assert_eq!(response.status(), StatusCode::FOUND);
+
+            let location = response.headers()[LOCATION].to_str().unwrap();
+            assert!(location.starts_with(&format!("{}/oauth/authorize?", mastodon.url)));
+
+            let location = reqwest::Url::parse(location).unwrap();
+            let query: HashMap<_, _> = location.query_pairs().into_owned().collect();
+            assert_eq!(query["client_id"], "mastodon-client-id");
+            assert_eq!(query["scope"], "write:statuses write:media");
+            states.push(query["state"].clone());
+        }
+
+        // The app is registered on the instance once.
+        assert_eq!(mastodon.registrations(), 1);
+
+        assert_eq!(callback(&routes, &states[1]).await, StatusCode::OK);
+
+        let session: Session =
+            sqlx::query_as("SELECT * FROM Sessions WHERE provider = 'MASTODON' AND bot_type = $1;")
+                .bind(BotType::ALTITUDE)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(session.access_token, "mastodon-access-token");
+        assert_eq!(session.refresh_token, None);
+        assert_eq!(
+            access_token(&pool).await.as_deref(),
+            Some("mastodon-access-token")
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn callback_with_valid_state_stores_session(pool: PgPool) {
+        let routes = routes(pool.clone());
+        insert_request(&pool, "valid-state", Utc::now()).await;
+
+        assert_eq!(callback(&routes, "valid-state").await, StatusCode::OK);
+        assert_eq!(access_token(&pool).await.as_deref(), Some("access-token-0"));
+
+        // The state was consumed by the first callback.
+        assert_eq!(
+            callback(&routes, "valid-state").await,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn callback_rejects_mismatched_state(pool: PgPool) {
+        let routes = routes(pool.clone());
+        insert_request(&pool, "issued-state", Utc::now()).await;
+
+        assert_eq!(
+            callback(&routes, "forged-state").await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(access_token(&pool).await, None);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn callback_rejects_expired_state(pool: PgPool) {
+        let routes = routes(pool.clone());
+        insert_request(
+            &pool,
+            "expired-state",
+            Utc::now() - AUTH_REQUEST_TTL - Duration::minutes(1),
+        )
+        .await;
+
+        assert_eq!(callback(&routes, "expired-state").await, StatusCode::GONE);
+        assert_eq!(access_token(&pool).await, None);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn callback_rejects_missing_code(pool: PgPool) {
+        let response = warp::test::request()
+            .path("/callback?state=some-state")
+            .reply(&routes(pool))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn recent_posts_hide_webhooks(pool: PgPool) {
+        sqlx::query("INSERT INTO Posts (bot_type, ident, text, status, provider, target, error) \
+            VALUES ($1, 'N650GD', 'a', 'PENDING', 'WEBHOOK', '0123456789abcdef', 'error sending request for url (https://hooks.example.com/token)');")
+            .bind(BotType::ALTITUDE)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let routes = history_routes(
+            pool,
+            HashMap::from([("altitude".to_string(), BotType::ALTITUDE)]),
+        );
+
+        let response = warp::test::request()
+            .path("/posts/altitude")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let posts: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(posts[0]["ident"], "N650GD");
+        assert_eq!(posts[0]["provider"], "WEBHOOK");
+        assert!(posts[0].get("target").is_none());
+        assert!(posts[0].get("error").is_none());
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres server in DATABASE_URL"]
+    async fn flight_history_counts_posted_tweets(pool: PgPool) {
+        sqlx::query("INSERT INTO Posts (bot_type, ident, text, status) VALUES ($1, 'N650GD', 'a', 'POSTED'), ($1, 'N650GD', 'b', 'SKIPPED'), ($1, 'N650GD', 'c', 'POSTED');")
+            .bind(BotType::ALTITUDE)
+            .execute(&pool)
+            .await
+            .unwrap();
+        // The same announcement delivered to a second network.
+        sqlx::query("INSERT INTO Posts (bot_type, ident, text, status, provider, announcement_id) \
+            SELECT bot_type, ident, text, status, 'MASTODON', announcement_id FROM Posts WHERE text = 'c';")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let routes = history_routes(
+            pool,
+            HashMap::from([("altitude".to_string(), BotType::ALTITUDE)]),
+        );
+
+        let response = warp::test::request()
+            .path("/posts/altitude/N650GD")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let history: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(history["times_posted"], 2);
+        assert!(history["last_posted_at"].is_string());
+
+        let response = warp::test::request()
+            .path("/posts/lowest")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
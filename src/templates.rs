@@ -0,0 +1,394 @@
+use std::str::FromStr;
+
+use crate::types::Flight;
+
+/// X's limit of a tweet's weighted length.
+pub const MAX_TWEET_LENGTH: usize = 280;
+
+/// Weight of a link, whatever its length, since X shortens it to a t.co link.
+const URL_LENGTH: usize = 23;
+
+/// Marks a shortened field.
+const ELLIPSIS: &str = "…";
+
+/// Fields shortened when a tweet is too long, the first one given up first.
+const TRUNCATION_ORDER: [Field; 3] = [Field::AircraftType, Field::Origin, Field::Destination];
+
+/// Speed of sound in knots at sea level and in the stratosphere of the standard atmosphere.
+const SEA_LEVEL_MACH_KNOTS: f64 = 661.47;
+const STRATOSPHERE_MACH_KNOTS: f64 = 573.57;
+/// Feet up to which the speed of sound decreases with the temperature.
+const TROPOPAUSE_FEET: f64 = 36_089.0;
+
+/// A value of the announced flight, referred to as `{{name}}` in a template.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Ident,
+    /// Feet and meters, e.g. `51000ft (15544.80m)`.
+    Altitude,
+    AltitudeFeet,
+    AltitudeMeters,
+    FlightLevel,
+    /// Knots and km/h, e.g. `512kts (948.22km/h)`.
+    Speed,
+    SpeedKnots,
+    SpeedKmh,
+    /// Groundspeed relative to the standard atmosphere's speed of sound at the flight's altitude.
+    SpeedMach,
+    Origin,
+    Destination,
+    AircraftType,
+    Link,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "ident" => Self::Ident,
+            "altitude" => Self::Altitude,
+            "altitude_ft" => Self::AltitudeFeet,
+            "altitude_m" => Self::AltitudeMeters,
+            "altitude_fl" => Self::FlightLevel,
+            "speed" => Self::Speed,
+            "speed_kts" => Self::SpeedKnots,
+            "speed_kmh" => Self::SpeedKmh,
+            "speed_mach" => Self::SpeedMach,
+            "origin" => Self::Origin,
+            "destination" => Self::Destination,
+            "aircraft_type" => Self::AircraftType,
+            "link" => Self::Link,
+            _ => return None,
+        })
+    }
+
+    fn render(self, flight: &Flight) -> String {
+        let feet = flight.altitude.map(|fl| fl * 100);
+        let meters = |feet: i32| format!("{:.2}m", feet as f32 * 0.3048);
+        let kmh = |knots: i32| format!("{:.2}km/h", knots as f32 * 1.852);
+
+        let value = match self {
+            Self::Ident => Some(flight.ident.clone()),
+            Self::Altitude => feet.map(|feet| format!("{}ft ({})", feet, meters(feet))),
+            Self::AltitudeFeet => feet.map(|feet| format!("{}ft", feet)),
+            Self::AltitudeMeters => feet.map(meters),
+            Self::FlightLevel => flight.altitude.map(|fl| format!("FL{:03}", fl)),
+            Self::Speed => flight
+                .groundspeed
+                .map(|knots| format!("{}kts ({})", knots, kmh(knots))),
+            Self::SpeedKnots => flight.groundspeed.map(|knots| format!("{}kts", knots)),
+            Self::SpeedKmh => flight.groundspeed.map(kmh),
+            Self::SpeedMach => flight.groundspeed.map(|knots| {
+                format!(
+                    "Mach {:.2}",
+                    knots as f64 / speed_of_sound(feet.unwrap_or(0))
+                )
+            }),
+            Self::Origin => Some(flight.origin.clone().unwrap_or("Unknown".to_string())),
+            Self::Destination => Some(flight.destination.clone().unwrap_or("Unknown".to_string())),
+            Self::AircraftType => Some(
+                flight
+                    .aircraft_type
+                    .clone()
+                    .unwrap_or("Unknown".to_string()),
+            ),
+            Self::Link => Some(flight.flightaware_url()),
+        };
+
+        value.unwrap_or("N/A".to_string())
+    }
+}
+
+/// Speed of sound in knots at `feet` in the standard atmosphere.
+fn speed_of_sound(feet: i32) -> f64 {
+    let feet = (feet as f64).clamp(0.0, TROPOPAUSE_FEET);
+    let ratio = feet / TROPOPAUSE_FEET;
+
+    // The speed of sound goes with the square root of the temperature, which falls linearly.
+    let sea_level = SEA_LEVEL_MACH_KNOTS.powi(2);
+    let stratosphere = STRATOSPHERE_MACH_KNOTS.powi(2);
+    (sea_level + (stratosphere - sea_level) * ratio).sqrt()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    Field(Field),
+}
+
+/// An announcement text with `{{name}}` placeholders for the flight's values, e.g.
+/// `Current highest flight: {{ident}} at {{altitude_fl}}`.
+///
+/// Rendered texts are kept within X's weighted length by shortening the aircraft type, origin
+/// and destination, in that order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TweetTemplate {
+    segments: Vec<Segment>,
+}
+
+impl FromStr for TweetTemplate {
+    type Err = String;
+
+    /// Parses a template and checks that it fits a tweet with its shortened fields.
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut segments = vec![];
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| format!("unclosed placeholder `{}`", &rest[start..]))?;
+            let name = rest[start + 2..start + end].trim();
+            let field = Field::from_name(name)
+                .ok_or_else(|| format!("unknown placeholder `{{{{{}}}}}`", name))?;
+
+            segments.push(Segment::Field(field));
+            rest = &rest[start + end + 2..];
+        }
+
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+
+        let template = Self { segments };
+
+        let length = weighted_length(&template.render(&longest_flight()));
+        if length > MAX_TWEET_LENGTH {
+            return Err(format!(
+                "renders up to {} characters, more than the {} of a tweet",
+                length, MAX_TWEET_LENGTH
+            ));
+        }
+
+        Ok(template)
+    }
+}
+
+impl TweetTemplate {
+    pub fn render(&self, flight: &Flight) -> String {
+        let mut values: Vec<(Option<Field>, String)> = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => (None, text.clone()),
+                Segment::Field(field) => (Some(*field), field.render(flight)),
+            })
+            .collect();
+
+        let join = |values: &[(Option<Field>, String)]| -> String {
+            values.iter().map(|(_, value)| value.as_str()).collect()
+        };
+
+        for field in TRUNCATION_ORDER {
+            for i in 0..values.len() {
+                if values[i].0 != Some(field) {
+                    continue;
+                }
+
+                let excess = weighted_length(&join(&values)).saturating_sub(MAX_TWEET_LENGTH);
+                if excess == 0 {
+                    return join(&values);
+                }
+
+                values[i].1 = shorten(&values[i].1, excess);
+            }
+        }
+
+        join(&values)
+    }
+}
+
+/// Drops characters off the end of `value` until it's `excess` lighter, marking the cut.
+fn shorten(value: &str, excess: usize) -> String {
+    let ellipsis = weighted_length(ELLIPSIS);
+    let length = weighted_length(value);
+
+    if length <= ellipsis {
+        return value.to_string();
+    }
+
+    let budget = length.saturating_sub(excess + ellipsis);
+    let mut shortened = String::new();
+    let mut used = 0;
+
+    for c in value.chars() {
+        used += char_weight(c);
+        if used > budget {
+            break;
+        }
+        shortened.push(c);
+    }
+
+    shortened.trim_end().to_string() + ELLIPSIS
+}
+
+/// Length of a text as X counts it: links weigh 23, most other scripts than Latin 2.
+pub fn weighted_length(text: &str) -> usize {
+    let mut length = 0;
+    let mut rest = text;
+
+    while let Some(start) = ["https://", "http://"]
+        .iter()
+        .filter_map(|scheme| rest.find(scheme))
+        .min()
+    {
+        length += rest[..start].chars().map(char_weight).sum::<usize>();
+        length += URL_LENGTH;
+
+        let end = rest[start..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |end| start + end);
+        rest = &rest[end..];
+    }
+
+    length + rest.chars().map(char_weight).sum::<usize>()
+}
+
+/// X counts the code points of Latin and a few punctuation blocks once, the rest twice.
+fn char_weight(c: char) -> usize {
+    match c as u32 {
+        0..=4351 | 8192..=8205 | 8208..=8223 | 8242..=8247 => 1,
+        _ => 2,
+    }
+}
+
+/// A flight whose fixed width values are as long as they get, to check templates against.
+fn longest_flight() -> Flight {
+    let text = "W".repeat(MAX_TWEET_LENGTH);
+
+    Flight {
+        ident: "WWWWWWWW".to_string(),
+        fa_flight_id: Some(text.clone()),
+        ranking: crate::types::BotType::ALTITUDE,
+        altitude: Some(999),
+        groundspeed: Some(9999),
+        destination: Some(text.clone()),
+        origin: Some(text.clone()),
+        aircraft_type: Some(text),
+        position_time: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bots::{AltitudeBot, GroundspeedBot, RankingBot},
+        types::BotType,
+    };
+
+    fn flight() -> Flight {
+        Flight {
+            ident: "UAE215".to_string(),
+            fa_flight_id: Some("UAE215-1717132800-schedule-0412".to_string()),
+            ranking: BotType::GROUNDSPEED,
+            altitude: Some(470),
+            groundspeed: Some(512),
+            destination: Some("Los Angeles Intl, Los Angeles [KLAX]".to_string()),
+            origin: Some("Dubai Int'l, Dubai [OMDB]".to_string()),
+            aircraft_type: Some("A388".to_string()),
+            position_time: None,
+        }
+    }
+
+    fn template(source: &str) -> TweetTemplate {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn default_templates_keep_the_original_layouts() {
+        let altitude = template(AltitudeBot.default_template());
+        let groundspeed = template(GroundspeedBot.default_template());
+
+        assert_eq!(
+            altitude.render(&flight()),
+            "Current highest flight: UAE215\n\
+            Altitude: 47000ft (14325.60m)\n\
+            Groundspeed: 512kts (948.22km/h)\n\
+            Origin: Dubai Int'l, Dubai [OMDB]\n\
+            Destination: Los Angeles Intl, Los Angeles [KLAX]\n\n\
+            More info:\nhttps://www.flightaware.com/live/flight/id/UAE215-1717132800-schedule-0412"
+        );
+        assert_eq!(
+            groundspeed.render(&Flight {
+                altitude: None,
+                origin: None,
+                ..flight()
+            }),
+            "Current fastest flight: UAE215\n\
+            Groundspeed: 512kts (948.22km/h)\n\
+            Altitude: N/A\n\
+            Origin: Unknown\n\
+            Destination: Los Angeles Intl, Los Angeles [KLAX]\n\n\
+            More info:\nhttps://www.flightaware.com/live/flight/id/UAE215-1717132800-schedule-0412"
+        );
+    }
+
+    #[test]
+    fn renders_units() {
+        let template = template(
+            "{{ altitude_ft }} {{altitude_m}} {{altitude_fl}} {{speed_kts}} {{speed_kmh}} \
+            {{speed_mach}} {{aircraft_type}}",
+        );
+
+        assert_eq!(
+            template.render(&flight()),
+            "47000ft 14325.60m FL470 512kts 948.22km/h Mach 0.89 A388"
+        );
+    }
+
+    #[test]
+    fn mach_follows_the_standard_atmosphere() {
+        assert!((speed_of_sound(0) - SEA_LEVEL_MACH_KNOTS).abs() < 0.01);
+        assert!((speed_of_sound(20_000) - 614.3).abs() < 0.5);
+        assert_eq!(speed_of_sound(51_000), STRATOSPHERE_MACH_KNOTS);
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        assert_eq!(
+            "Highest: {{callsign}}".parse::<TweetTemplate>(),
+            Err("unknown placeholder `{{callsign}}`".to_string())
+        );
+        assert_eq!(
+            "Highest: {{ident".parse::<TweetTemplate>(),
+            Err("unclosed placeholder `{{ident`".to_string())
+        );
+        assert!("{{ident}} ".repeat(40).parse::<TweetTemplate>().is_err());
+    }
+
+    #[test]
+    fn counts_like_x() {
+        assert_eq!(weighted_length("Altitude: 51000ft"), 17);
+        assert_eq!(
+            weighted_length("More info:\nhttps://www.flightaware.com/live/flight/id/UAE215"),
+            11 + URL_LENGTH
+        );
+        assert_eq!(weighted_length("高度 FL510"), 10);
+        assert_eq!(weighted_length("✈"), 2);
+    }
+
+    #[test]
+    fn shortens_lower_priority_fields_first() {
+        let template = template(&format!(
+            "{}\n{{{{ident}}}} {{{{link}}}}\nFrom {{{{origin}}}} to {{{{destination}}}} on {{{{aircraft_type}}}}",
+            "x".repeat(150)
+        ));
+        let flight = Flight {
+            origin: Some("O".repeat(60)),
+            destination: Some("D".repeat(60)),
+            aircraft_type: Some("Airbus A380-800".to_string()),
+            ..flight()
+        };
+
+        let text = template.render(&flight);
+
+        assert_eq!(weighted_length(&text), MAX_TWEET_LENGTH);
+        assert!(text.contains("UAE215 https://www.flightaware.com/live/flight/id/"));
+        assert!(text.ends_with(&format!(" to {} on …", "D".repeat(60))));
+        assert!(text.contains(&format!("From {}… to", "O".repeat(21))));
+    }
+}
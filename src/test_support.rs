@@ -0,0 +1,933 @@
+//! Local stand-ins for AeroAPI, X, Mastodon and Bluesky, used to run the bots end-to-end without real
+//! accounts.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use apalis::prelude::Data;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use shuttle_runtime::tokio;
+use sqlx::PgPool;
+use warp::{http::StatusCode, Filter};
+
+use crate::{
+    apis::{
+        BlueskyCredentials, MastodonCredentials, WebhookConfig, WebhookFormat, WebhookTarget,
+        XCredentials, XEndpoints,
+    },
+    bots::{JobContext, RankingBot},
+    config::{BotSettings, Config, DEFAULT_SCHEDULE},
+    types::{AuthProvider, BotType},
+};
+
+/// Stand-in for AeroAPI's `/flights/search` and `/flights/{id}/track`.
+pub struct MockAeroApi {
+    pub url: String,
+    requests: Arc<AtomicUsize>,
+    tracks: Arc<Mutex<HashMap<String, &'static str>>>,
+}
+
+impl MockAeroApi {
+    /// Serves `first_page` for new searches and the page registered for a `cursor` otherwise.
+    pub fn start(first_page: &'static str, pages: &[(&'static str, &'static str)]) -> Self {
+        let pages: Arc<HashMap<&str, &str>> = Arc::new(pages.iter().copied().collect());
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let counter = requests.clone();
+        let search = warp::path!("flights" / "search")
+            .and(warp::get())
+            .and(warp::header::<String>("x-apikey"))
+            .and(warp::query::<HashMap<String, String>>())
+            .map(move |_api_key: String, query: HashMap<String, String>| {
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                let body = match query.get("cursor") {
+                    Some(cursor) => pages.get(cursor.as_str()).copied().unwrap_or("{}"),
+                    None => first_page,
+                };
+
+                warp::reply::with_header(body, "content-type", "application/json")
+            });
+
+        // Flights without a registered track are unknown, like legs AeroAPI no longer has.
+        let tracks = Arc::new(Mutex::new(HashMap::new()));
+        let known_tracks = tracks.clone();
+        let track = warp::path!("flights" / String / "track")
+            .and(warp::get())
+            .and(warp::header::<String>("x-apikey"))
+            .map(move |fa_flight_id: String, _api_key: String| {
+                match known_tracks.lock().unwrap().get(&fa_flight_id) {
+                    Some(&body) => warp::reply::with_status(body, StatusCode::OK),
+                    None => warp::reply::with_status("{}", StatusCode::NOT_FOUND),
+                }
+            })
+            .map(|reply| warp::reply::with_header(reply, "content-type", "application/json"));
+
+        let (addr, server) = warp::serve(search.or(track)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        Self {
+            url: format!("http://{}", addr),
+            requests,
+            tracks,
+        }
+    }
+
+    /// Number of search pages requested so far.
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+
+    /// Serves `body` as the track of the leg `fa_flight_id`.
+    pub fn add_track(&self, fa_flight_id: &str, body: &'static str) {
+        self.tracks
+            .lock()
+            .unwrap()
+            .insert(fa_flight_id.to_string(), body);
+    }
+}
+
+#[derive(Default)]
+struct XState {
+    tweets: Vec<String>,
+    tweet_media: Vec<Vec<MockMedia>>,
+    replied_to: Vec<Option<String>>,
+    deleted: Vec<String>,
+    refreshes: usize,
+    failures: Vec<(StatusCode, &'static str)>,
+    uploads: Vec<MockMedia>,
+    upload_failures: Vec<StatusCode>,
+}
+
+/// An image uploaded to [`MockX`].
+#[derive(Clone, Debug, Default)]
+pub struct MockMedia {
+    pub media_type: String,
+    pub bytes: Vec<u8>,
+    /// Size of each `APPEND`ed chunk.
+    pub segments: Vec<usize>,
+    pub alt_text: Option<String>,
+    total_bytes: usize,
+    finalized: bool,
+}
+
+/// Stand-in for X's `/2/tweets`, `/2/media` and `/2/oauth2/token`.
+pub struct MockX {
+    pub url: String,
+    state: Arc<Mutex<XState>>,
+}
+
+impl MockX {
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(XState::default()));
+
+        let tweet_state = state.clone();
+        let tweets = warp::path!("2" / "tweets")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |body: Value| {
+                let mut state = tweet_state.lock().unwrap();
+
+                if !state.failures.is_empty() {
+                    let (status, detail) = state.failures.remove(0);
+                    let reply =
+                        warp::reply::json(&json!({ "detail": detail, "status": status.as_u16() }));
+                    return warp::reply::with_status(reply, status);
+                }
+
+                let mut media = vec![];
+                for id in body["media"]["media_ids"].as_array().into_iter().flatten() {
+                    match id.as_str().and_then(|id| id.parse::<usize>().ok()) {
+                        Some(i) if state.uploads.get(i).is_some_and(|m| m.finalized) => {
+                            media.push(state.uploads[i].clone());
+                        }
+                        _ => {
+                            let reply = warp::reply::json(&json!({ "detail": "invalid media id" }));
+                            return warp::reply::with_status(reply, StatusCode::BAD_REQUEST);
+                        }
+                    }
+                }
+
+                let text = body["text"].as_str().unwrap_or_default().to_string();
+                state.tweets.push(text.clone());
+                state.tweet_media.push(media);
+                let in_reply_to = body["reply"]["in_reply_to_tweet_id"].as_str();
+                state.replied_to.push(in_reply_to.map(String::from));
+                let id = state.tweets.len().to_string();
+
+                warp::reply::with_status(
+                    warp::reply::json(&json!({ "data": { "id": id, "text": text } })),
+                    StatusCode::CREATED,
+                )
+            });
+
+        let token_state = state.clone();
+        let token = warp::path!("2" / "oauth2" / "token")
+            .and(warp::post())
+            .and(warp::body::form())
+            .map(move |form: HashMap<String, String>| {
+                let mut state = token_state.lock().unwrap();
+
+                if form.get("grant_type").map(String::as_str) == Some("refresh_token") {
+                    state.refreshes += 1;
+                }
+
+                warp::reply::json(&json!({
+                    "access_token": format!("access-token-{}", state.refreshes),
+                    "token_type": "bearer",
+                    "expires_in": 7200,
+                    "refresh_token": format!("refresh-token-{}", state.refreshes),
+                    "scope": "tweet.write users.read tweet.read offline.access",
+                }))
+            });
+
+        // `INIT` and `FINALIZE` are sent as a form, `APPEND` as multipart for the binary chunk.
+        let command_state = state.clone();
+        let command = warp::path!("2" / "media" / "upload")
+            .and(warp::post())
+            .and(warp::body::form())
+            .map(move |form: HashMap<String, String>| {
+                let mut state = command_state.lock().unwrap();
+
+                if form.get("command").map(String::as_str) == Some("INIT") {
+                    if !state.upload_failures.is_empty() {
+                        let status = state.upload_failures.remove(0);
+                        let reply = warp::reply::json(&json!({ "detail": "upload rejected" }));
+                        return warp::reply::with_status(reply, status);
+                    }
+
+                    state.uploads.push(MockMedia {
+                        media_type: form["media_type"].clone(),
+                        total_bytes: form["total_bytes"].parse().unwrap(),
+                        ..MockMedia::default()
+                    });
+                    let id = (state.uploads.len() - 1).to_string();
+                    let reply = warp::reply::json(&json!({ "data": { "id": id } }));
+                    return warp::reply::with_status(reply, StatusCode::ACCEPTED);
+                }
+
+                // FINALIZE; the upload is reported as processing until its status is checked.
+                let id = form["media_id"].clone();
+                let media = &mut state.uploads[id.parse::<usize>().unwrap()];
+                if media.bytes.len() != media.total_bytes {
+                    let reply = warp::reply::json(&json!({ "detail": "incomplete upload" }));
+                    return warp::reply::with_status(reply, StatusCode::BAD_REQUEST);
+                }
+
+                let processing_info = json!({ "state": "pending", "check_after_secs": 0 });
+                let reply = warp::reply::json(
+                    &json!({ "data": { "id": id, "processing_info": processing_info } }),
+                );
+                warp::reply::with_status(reply, StatusCode::OK)
+            });
+
+        let append_state = state.clone();
+        let append = warp::path!("2" / "media" / "upload")
+            .and(warp::post())
+            .and(warp::header::<String>("content-type"))
+            .and(warp::body::bytes())
+            .map(
+                move |content_type: String, body: warp::hyper::body::Bytes| {
+                    let fields = multipart_fields(&content_type, &body);
+                    assert_eq!(fields["command"], b"APPEND");
+
+                    let mut state = append_state.lock().unwrap();
+                    let id = String::from_utf8_lossy(&fields["media_id"]).parse::<usize>();
+                    let media = &mut state.uploads[id.unwrap()];
+                    let segment_index = String::from_utf8_lossy(&fields["segment_index"]).parse();
+                    assert_eq!(segment_index, Ok(media.segments.len()));
+
+                    media.bytes.extend_from_slice(&fields["media"]);
+                    media.segments.push(fields["media"].len());
+                    StatusCode::NO_CONTENT
+                },
+            );
+
+        let status_state = state.clone();
+        let status = warp::path!("2" / "media" / "upload")
+            .and(warp::get())
+            .and(warp::query::<HashMap<String, String>>())
+            .map(move |query: HashMap<String, String>| {
+                let id = query["media_id"].clone();
+                status_state.lock().unwrap().uploads[id.parse::<usize>().unwrap()].finalized = true;
+
+                warp::reply::json(
+                    &json!({ "data": { "id": id, "processing_info": { "state": "succeeded" } } }),
+                )
+            });
+
+        let metadata_state = state.clone();
+        let metadata = warp::path!("2" / "media" / "metadata")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |body: Value| {
+                let id = body["id"].as_str().unwrap().parse::<usize>().unwrap();
+                let alt_text = body["metadata"]["alt_text"]["text"].as_str();
+                metadata_state.lock().unwrap().uploads[id].alt_text = alt_text.map(String::from);
+
+                warp::reply::json(&json!({ "data": { "associated_metadata": true } }))
+            });
+
+        let delete_state = state.clone();
+        let delete = warp::path!("2" / "tweets" / String)
+            .and(warp::delete())
+            .map(move |id: String| {
+                delete_state.lock().unwrap().deleted.push(id);
+                warp::reply::json(&json!({ "data": { "deleted": true } }))
+            });
+
+        let routes = tweets
+            .or(delete)
+            .or(token)
+            .or(command)
+            .or(append)
+            .or(status)
+            .or(metadata);
+        let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        Self {
+            url: format!("http://{}", addr),
+            state,
+        }
+    }
+
+    pub fn endpoints(&self) -> XEndpoints {
+        XEndpoints {
+            api_url: format!("{}/2", self.url),
+            auth_url: format!("{}/i/oauth2/authorize", self.url),
+            token_url: format!("{}/2/oauth2/token", self.url),
+            redirect_url: "http://localhost/callback".to_string(),
+        }
+    }
+
+    pub fn credentials(&self) -> XCredentials {
+        XCredentials {
+            client_id: "client-id".to_string(),
+            client_secret: "client-secret".to_string(),
+            endpoints: self.endpoints(),
+        }
+    }
+
+    /// Texts of the tweets posted so far.
+    pub fn tweets(&self) -> Vec<String> {
+        self.state.lock().unwrap().tweets.clone()
+    }
+
+    /// Images attached to each of the tweets posted so far.
+    pub fn tweet_media(&self) -> Vec<Vec<MockMedia>> {
+        self.state.lock().unwrap().tweet_media.clone()
+    }
+
+    /// Tweet each of the tweets posted so far replied to.
+    pub fn replied_to(&self) -> Vec<Option<String>> {
+        self.state.lock().unwrap().replied_to.clone()
+    }
+
+    /// Ids of the tweets deleted so far.
+    pub fn deleted(&self) -> Vec<String> {
+        self.state.lock().unwrap().deleted.clone()
+    }
+
+    /// Number of refresh token grants so far.
+    pub fn refreshes(&self) -> usize {
+        self.state.lock().unwrap().refreshes
+    }
+
+    /// Rejects the next tweet with `status`.
+    pub fn fail_next_tweet(&self, status: StatusCode, detail: &'static str) {
+        self.state.lock().unwrap().failures.push((status, detail));
+    }
+
+    /// Rejects the next media upload with `status`.
+    pub fn fail_next_upload(&self, status: StatusCode) {
+        self.state.lock().unwrap().upload_failures.push(status);
+    }
+}
+
+/// Fields of a `multipart/form-data` body by name.
+fn multipart_fields(content_type: &str, body: &[u8]) -> HashMap<String, Vec<u8>> {
+    let boundary = content_type
+        .split("boundary=")
+        .nth(1)
+        .expect("multipart body without boundary");
+    let delimiter = format!("\r\n--{}", boundary).into_bytes();
+
+    // Prepend the line break the first delimiter lacks, so all parts split alike.
+    let body = [b"\r\n", body].concat();
+    let mut fields = HashMap::new();
+
+    for part in split(&body, &delimiter).into_iter().skip(1) {
+        let Some(header_end) = find(part, b"\r\n\r\n") else {
+            continue;
+        };
+        let headers = String::from_utf8_lossy(&part[..header_end]);
+        let Some(name) = headers
+            .split("name=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+        else {
+            continue;
+        };
+
+        fields.insert(name.to_string(), part[header_end + 4..].to_vec());
+    }
+
+    fields
+}
+
+fn split<'a>(haystack: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = vec![];
+    let mut rest = haystack;
+
+    while let Some(i) = find(rest, delimiter) {
+        parts.push(&rest[..i]);
+        rest = &rest[i + delimiter.len()..];
+    }
+    parts.push(rest);
+
+    parts
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[derive(Default)]
+struct MastodonState {
+    statuses: Vec<Value>,
+    idempotency_keys: Vec<Option<String>>,
+    deleted: Vec<String>,
+    registrations: usize,
+    failures: Vec<StatusCode>,
+}
+
+/// Stand-in for a Mastodon instance's app registration, OAuth and statuses endpoints.
+pub struct MockMastodon {
+    pub url: String,
+    state: Arc<Mutex<MastodonState>>,
+}
+
+impl MockMastodon {
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(MastodonState::default()));
+
+        let apps_state = state.clone();
+        let apps = warp::path!("api" / "v1" / "apps")
+            .and(warp::post())
+            .and(warp::body::form())
+            .map(move |_form: HashMap<String, String>| {
+                apps_state.lock().unwrap().registrations += 1;
+                warp::reply::json(&json!({
+                    "id": "1",
+                    "name": "Aviation Bots",
+                    "client_id": "mastodon-client-id",
+                    "client_secret": "mastodon-client-secret",
+                }))
+            });
+
+        let token = warp::path!("oauth" / "token")
+            .and(warp::post())
+            .and(warp::body::form())
+            .map(|_form: HashMap<String, String>| {
+                warp::reply::json(&json!({
+                    "access_token": "mastodon-access-token",
+                    "token_type": "Bearer",
+                    "scope": "write:statuses write:media",
+                    "created_at": 1717160000,
+                }))
+            });
+
+        let status_state = state.clone();
+        let create = warp::path!("api" / "v1" / "statuses")
+            .and(warp::post())
+            .and(warp::header::optional::<String>("idempotency-key"))
+            .and(warp::body::json())
+            .map(move |key: Option<String>, body: Value| {
+                let mut state = status_state.lock().unwrap();
+
+                if !state.failures.is_empty() {
+                    let status = state.failures.remove(0);
+                    let reply = warp::reply::json(&json!({ "error": status.as_str() }));
+                    return warp::reply::with_status(reply, status);
+                }
+
+                state.statuses.push(body.clone());
+                state.idempotency_keys.push(key);
+                let id = format!("10{}", state.statuses.len());
+
+                warp::reply::with_status(
+                    warp::reply::json(&json!({ "id": id, "content": body["status"] })),
+                    StatusCode::OK,
+                )
+            });
+
+        let delete_state = state.clone();
+        let delete = warp::path!("api" / "v1" / "statuses" / String)
+            .and(warp::delete())
+            .map(move |id: String| {
+                delete_state.lock().unwrap().deleted.push(id.clone());
+                warp::reply::json(&json!({ "id": id }))
+            });
+
+        let routes = apps.or(token).or(create).or(delete);
+        let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        Self {
+            url: format!("http://{}", addr),
+            state,
+        }
+    }
+
+    pub fn credentials(&self) -> MastodonCredentials {
+        MastodonCredentials {
+            instance_url: self.url.clone(),
+            redirect_url: "http://localhost/callback".to_string(),
+        }
+    }
+
+    /// Texts of the statuses posted so far.
+    pub fn statuses(&self) -> Vec<String> {
+        self.requests()
+            .iter()
+            .map(|status| status["status"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    /// Bodies of the status requests so far.
+    pub fn requests(&self) -> Vec<Value> {
+        self.state.lock().unwrap().statuses.clone()
+    }
+
+    pub fn idempotency_keys(&self) -> Vec<Option<String>> {
+        self.state.lock().unwrap().idempotency_keys.clone()
+    }
+
+    /// Ids of the statuses deleted so far.
+    pub fn deleted(&self) -> Vec<String> {
+        self.state.lock().unwrap().deleted.clone()
+    }
+
+    /// Number of app registrations so far.
+    pub fn registrations(&self) -> usize {
+        self.state.lock().unwrap().registrations
+    }
+
+    /// Rejects the next status with `status`.
+    pub fn fail_next_status(&self, status: StatusCode) {
+        self.state.lock().unwrap().failures.push(status);
+    }
+}
+
+/// DID of the stand-in Bluesky account.
+pub const BLUESKY_DID: &str = "did:plc:aviationbot";
+
+#[derive(Default)]
+struct BlueskyState {
+    records: Vec<Value>,
+    deleted: Vec<String>,
+    logins: usize,
+    refreshes: usize,
+    expire_next_record: bool,
+    refresh_token_expired: bool,
+}
+
+/// Stand-in for a Bluesky PDS's session and repository XRPC endpoints.
+pub struct MockBluesky {
+    pub url: String,
+    state: Arc<Mutex<BlueskyState>>,
+}
+
+impl MockBluesky {
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(BlueskyState::default()));
+
+        let expired = || {
+            warp::reply::with_status(
+                warp::reply::json(
+                    &json!({ "error": "ExpiredToken", "message": "Token has expired" }),
+                ),
+                StatusCode::BAD_REQUEST,
+            )
+        };
+        let session = |n: usize| {
+            warp::reply::with_status(
+                warp::reply::json(&json!({
+                    "accessJwt": bluesky_jwt(&format!("access-{}", n), Utc::now() + Duration::hours(2)),
+                    "refreshJwt": format!("refresh-{}", n),
+                    "handle": "highestaircraft.bsky.social",
+                    "did": BLUESKY_DID,
+                })),
+                StatusCode::OK,
+            )
+        };
+
+        let login_state = state.clone();
+        let create_session = warp::path!("xrpc" / "com.atproto.server.createSession")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |body: Value| {
+                let mut state = login_state.lock().unwrap();
+
+                if body["password"] != "app-password" {
+                    return warp::reply::with_status(
+                        warp::reply::json(&json!({ "error": "AuthenticationRequired" })),
+                        StatusCode::UNAUTHORIZED,
+                    );
+                }
+
+                state.logins += 1;
+                state.refresh_token_expired = false;
+                session(state.logins + state.refreshes)
+            });
+
+        let refresh_state = state.clone();
+        let refresh_session = warp::path!("xrpc" / "com.atproto.server.refreshSession")
+            .and(warp::post())
+            .map(move || {
+                let mut state = refresh_state.lock().unwrap();
+
+                if state.refresh_token_expired {
+                    return expired();
+                }
+
+                state.refreshes += 1;
+                session(state.logins + state.refreshes)
+            });
+
+        let record_state = state.clone();
+        let create_record = warp::path!("xrpc" / "com.atproto.repo.createRecord")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |body: Value| {
+                let mut state = record_state.lock().unwrap();
+
+                if state.expire_next_record {
+                    state.expire_next_record = false;
+                    return expired();
+                }
+
+                state.records.push(body["record"].clone());
+                let n = state.records.len();
+
+                warp::reply::with_status(
+                    warp::reply::json(&json!({
+                        "uri": format!("at://{}/app.bsky.feed.post/{}", BLUESKY_DID, n),
+                        "cid": format!("cid-{}", n),
+                    })),
+                    StatusCode::OK,
+                )
+            });
+
+        let get_state = state.clone();
+        let get_record = warp::path!("xrpc" / "com.atproto.repo.getRecord")
+            .and(warp::get())
+            .and(warp::query::<HashMap<String, String>>())
+            .map(move |query: HashMap<String, String>| {
+                let state = get_state.lock().unwrap();
+                let rkey = &query["rkey"];
+                let n: usize = rkey.parse().unwrap();
+
+                warp::reply::json(&json!({
+                    "uri": format!("at://{}/app.bsky.feed.post/{}", BLUESKY_DID, rkey),
+                    "cid": format!("cid-{}", rkey),
+                    "value": state.records[n - 1],
+                }))
+            });
+
+        let delete_state = state.clone();
+        let delete_record = warp::path!("xrpc" / "com.atproto.repo.deleteRecord")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |body: Value| {
+                let rkey = body["rkey"].as_str().unwrap_or_default().to_string();
+                delete_state.lock().unwrap().deleted.push(rkey);
+                warp::reply::json(&json!({}))
+            });
+
+        let routes = create_session
+            .or(refresh_session)
+            .unify()
+            .or(create_record)
+            .unify()
+            .or(get_record)
+            .or(delete_record);
+        let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        Self {
+            url: format!("http://{}", addr),
+            state,
+        }
+    }
+
+    pub fn credentials(&self) -> BlueskyCredentials {
+        BlueskyCredentials {
+            service_url: self.url.clone(),
+        }
+    }
+
+    /// Post records created so far.
+    pub fn records(&self) -> Vec<Value> {
+        self.state.lock().unwrap().records.clone()
+    }
+
+    /// Record keys of the posts deleted so far.
+    pub fn deleted(&self) -> Vec<String> {
+        self.state.lock().unwrap().deleted.clone()
+    }
+
+    /// Number of app password logins so far.
+    pub fn logins(&self) -> usize {
+        self.state.lock().unwrap().logins
+    }
+
+    /// Number of refresh token grants so far.
+    pub fn refreshes(&self) -> usize {
+        self.state.lock().unwrap().refreshes
+    }
+
+    /// Rejects the next post as sent with an expired access token.
+    pub fn expire_access_token(&self) {
+        self.state.lock().unwrap().expire_next_record = true;
+    }
+
+    /// Rejects refreshes until the next login.
+    pub fn expire_refresh_token(&self) {
+        self.state.lock().unwrap().refresh_token_expired = true;
+    }
+}
+
+/// An unsigned token carrying just an expiry, like the ones a PDS issues.
+pub fn bluesky_jwt(subject: &str, expires_at: DateTime<Utc>) -> String {
+    let claims = json!({ "sub": subject, "exp": expires_at.timestamp() });
+    format!(
+        "eyJhbGciOiJIUzI1NiJ9.{}.signature",
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    )
+}
+
+/// A request received by [`MockWebhook`].
+#[derive(Clone, Debug)]
+pub struct WebhookRequest {
+    pub path: String,
+    pub body: Value,
+    pub raw_body: String,
+    pub timestamp: Option<String>,
+    pub signature: Option<String>,
+}
+
+#[derive(Default)]
+struct WebhookState {
+    requests: Vec<WebhookRequest>,
+    attempts: usize,
+    failures: Vec<StatusCode>,
+    /// Paths rejecting every request, with the status they reply.
+    rejecting: HashMap<String, StatusCode>,
+}
+
+/// Stand-in for chat webhooks, one per path.
+pub struct MockWebhook {
+    pub url: String,
+    state: Arc<Mutex<WebhookState>>,
+}
+
+impl MockWebhook {
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(WebhookState::default()));
+
+        let hook_state = state.clone();
+        let hook = warp::post()
+            .and(warp::path::full())
+            .and(warp::header::optional::<String>("x-webhook-timestamp"))
+            .and(warp::header::optional::<String>("x-webhook-signature"))
+            .and(warp::body::bytes())
+            .map(
+                move |path: warp::path::FullPath,
+                      timestamp: Option<String>,
+                      signature: Option<String>,
+                      body: warp::hyper::body::Bytes| {
+                    let mut state = hook_state.lock().unwrap();
+                    state.attempts += 1;
+
+                    let rejected = state.rejecting.get(path.as_str()).copied();
+                    if rejected.is_some() || !state.failures.is_empty() {
+                        let status = rejected.unwrap_or_else(|| state.failures.remove(0));
+                        return warp::reply::with_header(
+                            warp::reply::with_status("failed", status),
+                            "retry-after",
+                            "0",
+                        );
+                    }
+
+                    let raw_body = String::from_utf8_lossy(&body).into_owned();
+                    state.requests.push(WebhookRequest {
+                        path: path.as_str().to_string(),
+                        body: serde_json::from_str(&raw_body).unwrap_or_default(),
+                        raw_body,
+                        timestamp,
+                        signature,
+                    });
+
+                    warp::reply::with_header(
+                        warp::reply::with_status("", StatusCode::NO_CONTENT),
+                        "retry-after",
+                        "0",
+                    )
+                },
+            );
+
+        let (addr, server) = warp::serve(hook).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        Self {
+            url: format!("http://{}", addr),
+            state,
+        }
+    }
+
+    /// A webhook under `/{path}`.
+    pub fn target(&self, path: &str, format: WebhookFormat) -> WebhookTarget {
+        WebhookTarget {
+            url: format!("{}/{}", self.url, path),
+            format,
+        }
+    }
+
+    pub fn config(&self, targets: Vec<WebhookTarget>) -> WebhookConfig {
+        WebhookConfig {
+            targets,
+            secret: None,
+        }
+    }
+
+    /// Payloads accepted so far.
+    pub fn requests(&self) -> Vec<WebhookRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Requests received so far, including the failed ones.
+    pub fn attempts(&self) -> usize {
+        self.state.lock().unwrap().attempts
+    }
+
+    /// Rejects the next request with `status`.
+    pub fn fail_next(&self, status: StatusCode) {
+        self.state.lock().unwrap().failures.push(status);
+    }
+
+    /// Rejects every request to the webhook under `/{path}` with `status`.
+    pub fn reject(&self, path: &str, status: StatusCode) {
+        self.state
+            .lock()
+            .unwrap()
+            .rejecting
+            .insert(format!("/{}", path), status);
+    }
+}
+
+pub fn config(aero_api: &MockAeroApi, x: &MockX) -> Config {
+    Config {
+        aero_api_url: aero_api.url.clone(),
+        aero_api_key: "aero-api-key".to_string(),
+        aero_api_max_pages: 2,
+        aero_api_result_set_cost: 5_000,
+        aero_api_monthly_budget: None,
+        observation_retention_days: 90,
+        x_endpoints: x.endpoints(),
+        onboarding_token: None,
+    }
+}
+
+/// Context of a bot talking to the stand-in servers, announcing on X only.
+pub fn context<B: RankingBot>(
+    bot: B,
+    pool: &PgPool,
+    aero_api: &MockAeroApi,
+    x: &MockX,
+) -> Data<JobContext<B>> {
+    context_with(bot, pool, aero_api, x, |_| {})
+}
+
+/// Like [`context`], with `configure` e.g. adding networks or turning on dry runs.
+pub fn context_with<B: RankingBot>(
+    bot: B,
+    pool: &PgPool,
+    aero_api: &MockAeroApi,
+    x: &MockX,
+    configure: impl FnOnce(&mut JobContext<B>),
+) -> Data<JobContext<B>> {
+    let mut context = JobContext {
+        settings: BotSettings {
+            dry_run: false,
+            enabled: true,
+            threshold: bot.default_threshold(),
+            schedule: DEFAULT_SCHEDULE.parse().unwrap(),
+            template: bot.default_template().parse().unwrap(),
+        },
+        bot,
+        pool: pool.clone(),
+        config: config(aero_api, x),
+        credentials: x.credentials(),
+        mastodon: None,
+        bluesky: None,
+        webhooks: None,
+    };
+    configure(&mut context);
+
+    Data::new(context)
+}
+
+/// Stores an X session as if the bot had been onboarded.
+pub async fn insert_session(pool: &PgPool, bot_type: BotType, expires_at: DateTime<Utc>) {
+    sqlx::query("INSERT INTO Sessions (provider, bot_type, access_token, refresh_token, expires_at) VALUES ($1, $2, $3, $4, $5);")
+        .bind(AuthProvider::X)
+        .bind(bot_type)
+        .bind("access-token")
+        .bind("refresh-token")
+        .bind(expires_at)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+/// Stores a Mastodon session as if the bot had been onboarded.
+pub async fn insert_mastodon_session(pool: &PgPool, bot_type: BotType) {
+    sqlx::query("INSERT INTO Sessions (provider, bot_type, access_token) VALUES ($1, $2, $3);")
+        .bind(AuthProvider::MASTODON)
+        .bind(bot_type)
+        .bind("mastodon-access-token")
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+/// Stores a Bluesky session as if the bot had been onboarded.
+pub async fn insert_bluesky_session(pool: &PgPool, bot_type: BotType, expires_at: DateTime<Utc>) {
+    sqlx::query("INSERT INTO Sessions (provider, bot_type, access_token, refresh_token, expires_at, account_id, app_password) VALUES ($1, $2, $3, $4, $5, $6, $7);")
+        .bind(AuthProvider::BLUESKY)
+        .bind(bot_type)
+        .bind(bluesky_jwt("access-0", expires_at))
+        .bind("refresh-0")
+        .bind(expires_at)
+        .bind(BLUESKY_DID)
+        .bind("app-password")
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+/// Expiry of a session that doesn't need refreshing.
+pub fn valid_expiry() -> DateTime<Utc> {
+    Utc::now() + Duration::hours(1)
+}
@@ -4,4 +4,8 @@ use serde::{Deserialize, Serialize};
 #[sqlx(type_name = "AuthProvider")]
 pub enum AuthProvider {
     X,
+    MASTODON,
+    BLUESKY,
+    /// The bot's chat webhooks, which need no authorization.
+    WEBHOOK,
 }
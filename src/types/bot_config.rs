@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+
+use super::BotType;
+
+/// A bot's row in the Bots table. Unset columns fall back to the bot's secrets.
+#[derive(Debug, sqlx::FromRow, Clone, PartialEq)]
+pub struct BotConfig {
+    pub bot_type: BotType,
+    /// Cron expression of the bot's runs.
+    pub schedule: Option<String>,
+    pub threshold: Option<i32>,
+    pub enabled: Option<bool>,
+    /// Announcement template, see [`crate::templates::TweetTemplate`].
+    pub template: Option<String>,
+    /// Scheduled time of the bot's last run.
+    pub last_run_at: Option<DateTime<Utc>>,
+}
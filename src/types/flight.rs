@@ -1,11 +1,37 @@
+use chrono::{DateTime, Utc};
+
 use super::BotType;
 
 #[derive(Debug, sqlx::FromRow, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
 pub struct Flight {
     pub ident: String,
+    /// AeroAPI's id of the flight leg; the ident is reused across legs and days.
+    pub fa_flight_id: Option<String>,
     pub ranking: BotType,
     pub altitude: Option<i32>,
     pub groundspeed: Option<i32>,
     pub destination: Option<String>,
     pub origin: Option<String>,
+    /// ICAO type designator, e.g. `A388`.
+    pub aircraft_type: Option<String>,
+    /// Time of the last reported position the metrics were taken from.
+    pub position_time: Option<DateTime<Utc>>,
+}
+
+impl Flight {
+    /// Whether both are the same leg, falling back to the ident if AeroAPI didn't identify one.
+    pub fn is_same_flight(&self, other: &Flight) -> bool {
+        match (&self.fa_flight_id, &other.fa_flight_id) {
+            (Some(id), Some(other_id)) => id == other_id,
+            _ => self.ident == other.ident,
+        }
+    }
+
+    /// FlightAware page of the leg, or of the ident's latest leg if the leg isn't known.
+    pub fn flightaware_url(&self) -> String {
+        match &self.fa_flight_id {
+            Some(id) => format!("https://www.flightaware.com/live/flight/id/{}", id),
+            None => format!("https://www.flightaware.com/live/flight/{}", self.ident),
+        }
+    }
 }
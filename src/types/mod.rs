@@ -1,9 +1,19 @@
 mod auth_provider;
+mod bot_config;
 mod bot_type;
 mod flight;
+mod observation;
+mod post;
+mod post_status;
 mod session;
+mod track_point;
 
 pub use auth_provider::AuthProvider;
+pub use bot_config::BotConfig;
 pub use bot_type::BotType;
 pub use flight::Flight;
+pub use observation::Observation;
+pub use post::Post;
+pub use post_status::PostStatus;
 pub use session::Session;
+pub use track_point::TrackPoint;
@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+
+use super::BotType;
+
+/// A flight position returned by an AeroAPI search, kept for later analysis.
+#[derive(Debug, sqlx::FromRow, serde::Serialize, Clone, PartialEq)]
+pub struct Observation {
+    /// Ranking whose search returned the flight.
+    pub bot_type: BotType,
+    pub ident: String,
+    pub fa_flight_id: Option<String>,
+    /// Flight level (hundreds of feet).
+    pub altitude: Option<i32>,
+    /// Knots.
+    pub groundspeed: Option<i32>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub heading: Option<i32>,
+    pub aircraft_type: Option<String>,
+    pub position_time: Option<DateTime<Utc>>,
+    /// When the search ran.
+    pub fetched_at: DateTime<Utc>,
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+
+use super::{AuthProvider, BotType, Flight, PostStatus, TrackPoint};
+
+#[derive(Debug, sqlx::FromRow, Clone, PartialEq)]
+pub struct Post {
+    pub id: i64,
+    pub bot_type: BotType,
+    pub ident: String,
+    pub fa_flight_id: Option<String>,
+    /// Network the row is delivered to, unset for runs that announced nothing.
+    pub provider: Option<AuthProvider>,
+    /// [`crate::apis::WebhookTarget::key`] of the webhook a `WEBHOOK` row is delivered to.
+    pub target: Option<String>,
+    /// Shared by the rows delivering the same announcement to different networks.
+    pub announcement_id: i64,
+    /// Id of the post on the network.
+    pub external_id: Option<String>,
+    pub text: String,
+    /// The flight as it was announced.
+    pub flight: Option<Json<Flight>>,
+    /// Positions of the leg to draw a map of, on the networks that take images.
+    pub track: Option<Json<Vec<TrackPoint>>>,
+    pub status: PostStatus,
+    /// Last delivery error.
+    pub error: Option<String>,
+    /// When the announcement was made, i.e. queued for delivery.
+    pub posted_at: DateTime<Utc>,
+    /// Delivery attempts so far.
+    pub attempts: i32,
+    /// Earliest time a failed delivery is tried again.
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
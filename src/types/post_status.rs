@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, PartialOrd, sqlx::Type, Deserialize, Serialize)]
+#[sqlx(type_name = "PostStatus")]
+#[allow(non_camel_case_types)]
+pub enum PostStatus {
+    /// Tweeted.
+    POSTED,
+    /// Rendered in dry-run mode.
+    DRY_RUN,
+    /// Not tweeted because the flight was announced before.
+    SKIPPED,
+    /// Tweeting failed too often, given up.
+    FAILED,
+    /// Waiting to be tweeted by the delivery worker.
+    PENDING,
+}
@@ -1,3 +1,5 @@
+use chrono::{DateTime, Utc};
+
 use super::{AuthProvider, BotType};
 
 #[derive(Debug, sqlx::FromRow, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
@@ -5,5 +7,19 @@ pub struct Session {
     pub provider: AuthProvider,
     pub bot_type: BotType,
     pub access_token: String,
-    pub refresh_token: String,
+    /// Not issued by every provider, e.g. Mastodon tokens don't expire.
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The account's id where the provider needs it for requests, e.g. a Bluesky DID.
+    pub account_id: Option<String>,
+    /// Bluesky app password, to open a new session once the refresh token expired.
+    pub app_password: Option<String>,
+}
+
+impl Session {
+    /// Whether the access token expires within `margin`. Tokens of unknown age count as expired.
+    pub fn is_expired(&self, margin: chrono::Duration) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| expires_at - margin <= Utc::now())
+    }
 }
@@ -0,0 +1,8 @@
+/// A position of a flight's track as reported by AeroAPI.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct TrackPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Degrees clockwise from north.
+    pub heading: Option<i32>,
+}
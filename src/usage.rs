@@ -0,0 +1,94 @@
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use sqlx::PgPool;
+
+/// Pages of a search set aside from the monthly AeroAPI budget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reservation {
+    pub month: NaiveDate,
+    /// Pages the search may fetch.
+    pub pages: u32,
+    /// Price of a page in millionths of a dollar.
+    pub page_cost: i64,
+    /// Budget left after the reservation, if there is a budget.
+    pub remaining: Option<i64>,
+}
+
+/// Sets aside up to `wanted` pages that fit into this month's `budget`.
+///
+/// The usage row stays locked while reserving, so concurrent jobs can't overspend together.
+pub async fn reserve_pages(
+    pool: &PgPool,
+    budget: Option<i64>,
+    page_cost: i64,
+    wanted: u32,
+    now: DateTime<Utc>,
+) -> Result<Reservation, sqlx::Error> {
+    let month = month_of(now);
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("INSERT INTO AeroApiUsage (month) VALUES ($1) ON CONFLICT DO NOTHING;")
+        .bind(month)
+        .execute(&mut *tx)
+        .await?;
+
+    let spent: i64 =
+        sqlx::query_scalar("SELECT cost FROM AeroApiUsage WHERE month = $1 FOR UPDATE;")
+            .bind(month)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    let pages = match budget {
+        Some(budget) if page_cost > 0 => {
+            let affordable = (budget - spent).max(0) / page_cost;
+            wanted.min(affordable.try_into().unwrap_or(u32::MAX))
+        }
+        _ => wanted,
+    };
+    let reserved = i64::from(pages) * page_cost;
+
+    sqlx::query("UPDATE AeroApiUsage SET cost = cost + $2 WHERE month = $1;")
+        .bind(month)
+        .bind(reserved)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Reservation {
+        month,
+        pages,
+        page_cost,
+        remaining: budget.map(|budget| budget - spent - reserved),
+    })
+}
+
+/// Books the pages a search actually fetched and releases the rest of its reservation.
+pub async fn settle(
+    pool: &PgPool,
+    reservation: &Reservation,
+    fetched: u32,
+) -> Result<(), sqlx::Error> {
+    let unused = i64::from(reservation.pages.saturating_sub(fetched)) * reservation.page_cost;
+
+    sqlx::query(
+        "UPDATE AeroApiUsage SET result_sets = result_sets + $2, cost = cost - $3 WHERE month = $1;",
+    )
+    .bind(reservation.month)
+    .bind(i32::try_from(fetched).unwrap_or(i32::MAX))
+    .bind(unused)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Formats millionths of a dollar.
+pub fn dollars(micros: i64) -> String {
+    format!("${:.3}", micros as f64 / 1_000_000.0)
+}
+
+fn month_of(time: DateTime<Utc>) -> NaiveDate {
+    time.date_naive()
+        .with_day(1)
+        .expect("every month has a first day")
+}